@@ -1,6 +1,66 @@
-use std::{collections::HashSet, io::Read as _};
+use std::io::Read as _;
 
-const EPXANSION_FACTOR: i64 = 999_999;
+use clap::Parser as _;
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// How many times larger each empty row and column becomes after expansion
+    #[arg(default_value_t = 1_000_000)]
+    expansion_factor: i64,
+
+    /// How to measure the distance between two galaxies
+    #[arg(long, value_enum, default_value_t = Metric::Manhattan)]
+    metric: Metric,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Print the sum of the distances between every pair of galaxies (default)
+    Sum,
+
+    /// Print the closest and farthest pairs of galaxies
+    Extremes,
+
+    /// Print the galaxies nearest to a given galaxy
+    Nearest {
+        /// The galaxy to search from, numbered from 1 in reading order
+        galaxy: usize,
+
+        /// How many galaxies to print
+        #[arg(short, default_value_t = 1)]
+        k: usize,
+    },
+
+    /// Print a histogram of the distances between every pair of galaxies
+    Histogram {
+        /// How many equal-width buckets to split the distances into
+        #[arg(long, default_value_t = 10)]
+        buckets: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+    Euclidean,
+}
+
+impl Metric {
+    fn distance(&self, a: &Position, b: &Position) -> f64 {
+        let row_diff = (a.row - b.row).abs() as f64;
+        let col_diff = (a.col - b.col).abs() as f64;
+
+        match self {
+            Self::Manhattan => row_diff + col_diff,
+            Self::Chebyshev => row_diff.max(col_diff),
+            Self::Euclidean => row_diff.hypot(col_diff),
+        }
+    }
+}
 
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
@@ -13,81 +73,209 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+    eyre::ensure!(
+        args.expansion_factor >= 1,
+        "expansion factor must be at least 1"
+    );
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
     let image: GalaxyImage = input.parse()?;
-    let galaxies = image.galaxies();
+    let galaxies = image.galaxies(args.expansion_factor);
 
-    let galaxy_pairs = galaxies
-        .iter()
-        .flat_map(|a| {
-            galaxies.iter().filter_map(move |b| match a.cmp(&b) {
-                std::cmp::Ordering::Less => Some((a, b)),
-                std::cmp::Ordering::Equal => None,
-                std::cmp::Ordering::Greater => Some((b, a)),
-            })
-        })
-        .collect::<HashSet<_>>();
+    match args.command.unwrap_or(Command::Sum) {
+        Command::Sum => match args.metric {
+            Metric::Manhattan => {
+                let sum = sum_of_distances(&galaxies);
+                println!("{sum}");
+            }
+            Metric::Chebyshev => {
+                let sum = sum_of_chebyshev_distances(&galaxies);
+                println!("{sum}");
+            }
+            Metric::Euclidean => {
+                let sum: f64 = galaxy_pairs(&galaxies)
+                    .map(|(a, b)| args.metric.distance(&galaxies[a], &galaxies[b]))
+                    .sum();
+                println!("{sum}");
+            }
+        },
+        Command::Extremes => {
+            let mut closest: Option<(usize, usize, f64)> = None;
+            let mut farthest: Option<(usize, usize, f64)> = None;
+            for (a, b) in galaxy_pairs(&galaxies) {
+                let distance = args.metric.distance(&galaxies[a], &galaxies[b]);
+                if closest.is_none_or(|(_, _, closest)| distance < closest) {
+                    closest = Some((a, b, distance));
+                }
+                if farthest.is_none_or(|(_, _, farthest)| distance > farthest) {
+                    farthest = Some((a, b, distance));
+                }
+            }
 
-    let sum: i64 = galaxy_pairs.iter().map(|(a, b)| a.distance_to(b)).sum();
+            let (Some(closest), Some(farthest)) = (closest, farthest) else {
+                eyre::bail!("need at least 2 galaxies");
+            };
 
-    println!("{sum}");
+            for (name, (a, b, distance)) in [("closest", closest), ("farthest", farthest)] {
+                println!(
+                    "{name}: galaxy {} {} and galaxy {} {}: {distance}",
+                    a + 1,
+                    galaxies[a],
+                    b + 1,
+                    galaxies[b],
+                );
+            }
+        }
+        Command::Nearest { galaxy, k } => {
+            eyre::ensure!(
+                (1..=galaxies.len()).contains(&galaxy),
+                "galaxy {galaxy} not found (image has {} galaxies)",
+                galaxies.len()
+            );
+            let from = galaxy - 1;
+
+            let mut neighbors = galaxies
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != from)
+                .map(|(i, other)| (i, args.metric.distance(&galaxies[from], other)))
+                .collect::<Vec<_>>();
+            neighbors.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+            for (i, distance) in neighbors.into_iter().take(k) {
+                println!("galaxy {} {}: {distance}", i + 1, galaxies[i]);
+            }
+        }
+        Command::Histogram { buckets } => {
+            eyre::ensure!(buckets >= 1, "need at least 1 bucket");
+
+            let distances = galaxy_pairs(&galaxies)
+                .map(|(a, b)| args.metric.distance(&galaxies[a], &galaxies[b]))
+                .collect::<Vec<_>>();
+            let min = distances.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = distances.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            eyre::ensure!(!distances.is_empty(), "need at least 2 galaxies");
+
+            let width = ((max - min) / buckets as f64).max(f64::MIN_POSITIVE);
+            let mut counts = vec![0usize; buckets];
+            for distance in distances {
+                let bucket = ((distance - min) / width) as usize;
+                counts[bucket.min(buckets - 1)] += 1;
+            }
+
+            for (bucket, count) in counts.into_iter().enumerate() {
+                let start = min + width * bucket as f64;
+                let end = start + width;
+                println!("{start:.1}..{end:.1}: {count}");
+            }
+        }
+    }
 
     Ok(())
 }
 
+fn galaxy_pairs(galaxies: &[Position]) -> impl Iterator<Item = (usize, usize)> {
+    let num_galaxies = galaxies.len();
+    (0..num_galaxies).flat_map(move |a| ((a + 1)..num_galaxies).map(move |b| (a, b)))
+}
+
 struct GalaxyImage {
     rows: Vec<Vec<Pixel>>,
 }
 
 impl GalaxyImage {
-    fn galaxies(&self) -> Vec<Position> {
-        let num_rows = self.rows.len();
+    fn galaxies(&self, expansion_factor: i64) -> Vec<Position> {
         let num_cols = self.rows.first().map(|row| row.len()).unwrap_or(0);
 
-        let mut row_expansions = vec![];
-        let mut col_expansions = vec![];
+        // Running count of empty rows/columns before each index, so each
+        // galaxy's expanded position can be looked up directly
+        let row_offsets = expansion_offsets(
+            self.rows
+                .iter()
+                .map(|row| row.iter().all(|cell| *cell == Pixel::Empty)),
+        );
+        let col_offsets = expansion_offsets(
+            (0..num_cols).map(|j| self.rows.iter().all(|row| row[j] == Pixel::Empty)),
+        );
 
-        for i in 0..num_rows {
-            if self.rows[i].iter().all(|cell| *cell == Pixel::Empty) {
-                row_expansions.push(i);
-            }
-        }
-
-        for j in 0..num_cols {
-            if self.rows.iter().all(|row| row[j] == Pixel::Empty) {
-                col_expansions.push(j);
-            }
-        }
-
-        let row_expansions = &row_expansions;
-        let col_expansions = &col_expansions;
+        let added_size = expansion_factor - 1;
 
         self.rows
             .iter()
             .enumerate()
             .flat_map(|(row, cells)| {
-                cells.iter().enumerate().filter_map(move |(col, cell)| {
-                    let num_row_expansions =
-                        row_expansions.iter().take_while(|i| **i < row).count();
-                    let num_col_expansions =
-                        col_expansions.iter().take_while(|j| **j < col).count();
-
-                    match cell {
+                let row_offsets = &row_offsets;
+                let col_offsets = &col_offsets;
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(col, cell)| match cell {
                         Pixel::Empty => None,
                         Pixel::Galaxy => Some(Position {
-                            row: (row as i64) + (num_row_expansions as i64 * EPXANSION_FACTOR),
-                            col: (col as i64) + (num_col_expansions as i64 * EPXANSION_FACTOR),
+                            row: (row as i64) + (row_offsets[row] * added_size),
+                            col: (col as i64) + (col_offsets[col] * added_size),
                         }),
-                    }
-                })
+                    })
             })
             .collect()
     }
 }
 
+fn expansion_offsets(is_empty: impl Iterator<Item = bool>) -> Vec<i64> {
+    is_empty
+        .scan(0, |num_empty, empty| {
+            let offset = *num_empty;
+            if empty {
+                *num_empty += 1;
+            }
+            Some(offset)
+        })
+        .collect()
+}
+
+/// Sum the Manhattan distances between every pair of galaxies. Each axis
+/// is summed independently: after sorting, the coordinate at index `i`
+/// contributes `i * coord - (sum of all smaller coords)`.
+fn sum_of_distances(galaxies: &[Position]) -> i64 {
+    let rows = galaxies.iter().map(|galaxy| galaxy.row).collect();
+    let cols = galaxies.iter().map(|galaxy| galaxy.col).collect();
+
+    sum_of_axis_distances(rows) + sum_of_axis_distances(cols)
+}
+
+/// Sum the Chebyshev distances between every pair of galaxies. Rotating
+/// each position by 45 degrees turns Chebyshev distance into half of the
+/// Manhattan distance, so this reuses the same per-axis sums.
+fn sum_of_chebyshev_distances(galaxies: &[Position]) -> i64 {
+    let sums = galaxies
+        .iter()
+        .map(|galaxy| galaxy.row + galaxy.col)
+        .collect();
+    let diffs = galaxies
+        .iter()
+        .map(|galaxy| galaxy.row - galaxy.col)
+        .collect();
+
+    (sum_of_axis_distances(sums) + sum_of_axis_distances(diffs)) / 2
+}
+
+fn sum_of_axis_distances(mut coords: Vec<i64>) -> i64 {
+    coords.sort_unstable();
+
+    let mut prefix_sum = 0;
+    let mut total = 0;
+    for (i, coord) in coords.into_iter().enumerate() {
+        total += (i as i64) * coord - prefix_sum;
+        prefix_sum += coord;
+    }
+
+    total
+}
+
 impl std::str::FromStr for GalaxyImage {
     type Err = eyre::Error;
 
@@ -153,11 +341,8 @@ struct Position {
     col: i64,
 }
 
-impl Position {
-    fn distance_to(&self, other: &Self) -> i64 {
-        let row_diff = self.row - other.row;
-        let col_diff = self.col - other.col;
-
-        i64::abs(row_diff) + i64::abs(col_diff)
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.row, self.col)
     }
 }
@@ -2,15 +2,71 @@
 // inspired by this comment:
 // - https://www.reddit.com/r/adventofcode/comments/18ge41g/comment/kd0b23d/
 
-use std::{
-    collections::HashMap,
-    io::Read as _,
-    str::FromStr,
-    sync::{OnceLock, RwLock},
-};
+use std::{io::Read as _, ops::ControlFlow, str::FromStr};
 
+use clap::Parser as _;
 use eyre::OptionExt;
-use smallvec::SmallVec;
+use rand::SeedableRng as _;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// How many copies of each row to join together
+    #[arg(long, default_value_t = 5)]
+    unfold_factor: usize,
+
+    /// The cell placed between each copy of an unfolded row
+    #[arg(long, default_value_t = PartialCell::Unknown, value_parser = parse_cell)]
+    separator: PartialCell,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Print the total number of arrangements across all rows (default)
+    Count,
+
+    /// Print each valid arrangement for a row
+    List {
+        /// The row to list arrangements for, numbered from 1
+        row: usize,
+
+        /// Stop after printing this many arrangements
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+
+    /// Print arrangements for a row chosen uniformly at random
+    Sample {
+        /// The row to sample arrangements from, numbered from 1
+        row: usize,
+
+        /// How many arrangements to print
+        #[arg(short = 'n', long, default_value_t = 1)]
+        count: usize,
+
+        /// Seed for the random number generator
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Print the total number of arrangements for each unfold factor from
+    /// 1 up to the given factor (ignores `--unfold-factor`)
+    Growth {
+        /// The largest unfold factor to count arrangements for
+        max_factor: usize,
+    },
+}
+
+fn parse_cell(value: &str) -> eyre::Result<PartialCell> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c.try_into(),
+        _ => eyre::bail!("expected a single cell, got {value:?}"),
+    }
+}
 
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
@@ -23,34 +79,103 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
-    let mut rows = input
+    let folded_rows = input
         .lines()
         .map(|line| line.parse())
         .collect::<eyre::Result<Vec<Row>>>()?;
 
+    let mut rows = folded_rows.clone();
     for row in &mut rows {
-        row.unfold();
+        row.unfold(args.unfold_factor, args.separator);
     }
 
-    tracing::info!("starting");
-    let total_solutions: u64 = rows
-        .into_iter()
+    match args.command.unwrap_or(Command::Count) {
+        Command::Count => {
+            tracing::info!("starting");
+            let total_solutions = total_solutions(rows);
+            tracing::info!("complete");
+            println!("{total_solutions}");
+        }
+        Command::Growth { max_factor } => {
+            let mut previous_total = None;
+            for factor in 1..=max_factor {
+                let mut rows = folded_rows.clone();
+                for row in &mut rows {
+                    row.unfold(factor, args.separator);
+                }
+
+                let total = total_solutions(rows);
+                match previous_total {
+                    Some(previous_total) if previous_total > 0 => {
+                        let growth = total as f64 / previous_total as f64;
+                        println!("{factor}: {total} (x{growth:.3})");
+                    }
+                    _ => {
+                        println!("{factor}: {total}");
+                    }
+                }
+                previous_total = Some(total);
+            }
+        }
+        Command::List { row, limit } => {
+            let row = get_row(&rows, row)?;
+            let table = row.solution_table();
+            tracing::info!("{} solution(s)", table.total());
+
+            let mut remaining = limit.unwrap_or(usize::MAX);
+            table.for_each_solution(|cells| {
+                if remaining == 0 {
+                    return ControlFlow::Break(());
+                }
+                remaining -= 1;
+
+                println!("{}", DisplayCells(cells));
+                ControlFlow::Continue(())
+            });
+        }
+        Command::Sample { row, count, seed } => {
+            let row = get_row(&rows, row)?;
+            let table = row.solution_table();
+            eyre::ensure!(table.total() > 0, "row has no solutions");
+
+            let mut rng = match seed {
+                Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+                None => rand::rngs::StdRng::from_entropy(),
+            };
+            for _ in 0..count {
+                let cells = table.sample(&mut rng);
+                println!("{}", DisplayCells(&cells));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn total_solutions(rows: Vec<Row>) -> u128 {
+    rows.into_par_iter()
         .enumerate()
         .map(|(n, row)| {
-            let solutions =
-                num_solutions(row.cells.into(), row.constraints.into(), Contiguity::Normal);
-            tracing::debug!("row {n}: {solutions} solution(s)");
+            let solutions = row.num_solutions();
+            tracing::debug!(
+                "row {n} ({}): {solutions} solution(s)",
+                DisplayCells(&row.cells)
+            );
             solutions
         })
-        .sum();
-    tracing::info!("complete");
-    println!("{total_solutions}");
+        .sum()
+}
 
-    Ok(())
+fn get_row(rows: &[Row], row: usize) -> eyre::Result<&Row> {
+    row.checked_sub(1)
+        .and_then(|index| rows.get(index))
+        .ok_or_else(|| eyre::eyre!("row {row} not found (input has {} rows)", rows.len()))
 }
 
 #[derive(Debug, Clone)]
@@ -60,145 +185,159 @@ struct Row {
 }
 
 impl Row {
-    fn unfold(&mut self) {
-        let new_cells = (0..5)
-            .flat_map(|_| {
-                [PartialCell::Unknown]
-                    .into_iter()
-                    .chain(self.cells.iter().copied())
-            })
+    fn unfold(&mut self, copies: usize, separator: PartialCell) {
+        let new_cells = (0..copies)
+            .flat_map(|_| [separator].into_iter().chain(self.cells.iter().copied()))
             .skip(1)
             .collect();
-        let new_constraints = (0..5)
+        let new_constraints = (0..copies)
             .flat_map(|_| self.constraints.iter().cloned())
             .collect();
 
         self.cells = new_cells;
         self.constraints = new_constraints;
     }
+
+    fn num_solutions(&self) -> u128 {
+        self.solution_table().total()
+    }
+
+    fn solution_table(&self) -> SolutionTable<'_> {
+        let num_cells = self.cells.len();
+        let num_groups = self.constraints.len();
+
+        // `next_operational[i]` is the index of the first cell at or after
+        // `i` that's known to be operational, so we can check whether a
+        // group of damaged springs fits starting at `i` in constant time
+        let mut next_operational = vec![num_cells; num_cells + 1];
+        for i in (0..num_cells).rev() {
+            next_operational[i] = match self.cells[i] {
+                PartialCell::Operational => i,
+                PartialCell::Damaged | PartialCell::Unknown => next_operational[i + 1],
+            };
+        }
+
+        let mut table = SolutionTable {
+            row: self,
+            next_operational,
+            solutions: vec![vec![0u128; num_groups + 1]; num_cells + 2],
+        };
+        table.solutions[num_cells][num_groups] = 1;
+        table.solutions[num_cells + 1][num_groups] = 1;
+
+        // Rows are filled in from the end, so each row only depends on
+        // later rows
+        for i in (0..num_cells).rev() {
+            for j in (0..=num_groups).rev() {
+                let (operational, damaged) = table.branches(i, j);
+                table.solutions[i][j] = operational + damaged.map_or(0, |(_, count)| count);
+            }
+        }
+
+        table
+    }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
-enum Contiguity {
-    #[default]
-    Normal,
-    ContinuesGroup,
-    BreaksGroup,
+struct SolutionTable<'a> {
+    row: &'a Row,
+    next_operational: Vec<usize>,
+
+    /// `solutions[i][j]` is the number of ways to arrange the groups from
+    /// `j` onwards within the cells from `i` onwards.
+    solutions: Vec<Vec<u128>>,
 }
 
-fn num_solutions(
-    cells: SmallVec<[PartialCell; 128]>,
-    constraints: SmallVec<[u8; 128]>,
-    contiguity: Contiguity,
-) -> u64 {
-    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-    struct NumSolutionsCacheKey {
-        cells: SmallVec<[PartialCell; 128]>,
-        constraints: SmallVec<[u8; 128]>,
-        contiguity: Contiguity,
+impl SolutionTable<'_> {
+    fn total(&self) -> u128 {
+        self.solutions[0][0]
     }
 
-    static NUM_SOLUTIONS_CACHE: OnceLock<RwLock<HashMap<NumSolutionsCacheKey, u64>>> =
-        OnceLock::new();
+    /// Returns the number of solutions where cell `i` is operational, and
+    /// (if group `j` can start at cell `i`) the index where the group ends
+    /// along with the number of solutions where it starts there.
+    fn branches(&self, i: usize, j: usize) -> (u128, Option<(usize, u128)>) {
+        let cells = &self.row.cells;
+        let num_cells = cells.len();
 
-    let key = NumSolutionsCacheKey {
-        cells,
-        constraints,
-        contiguity,
-    };
-
-    let num_solutions_cache = NUM_SOLUTIONS_CACHE.get_or_init(RwLock::default);
+        let operational = if cells[i] != PartialCell::Damaged {
+            self.solutions[i + 1][j]
+        } else {
+            0
+        };
 
-    {
-        let num_solutions_cache = num_solutions_cache.read().unwrap();
-        if let Some(solutions) = num_solutions_cache.get(&key) {
-            return *solutions;
+        let damaged = if cells[i] != PartialCell::Operational && j < self.row.constraints.len() {
+            let group_end = i + usize::from(self.row.constraints[j]);
+            let fits = group_end <= num_cells
+                && self.next_operational[i] >= group_end
+                && cells.get(group_end) != Some(&PartialCell::Damaged);
+            fits.then(|| (group_end, self.solutions[group_end + 1][j + 1]))
+        } else {
+            None
         };
+
+        (operational, damaged)
     }
 
-    let solutions = compute_num_solutions(&key.cells, &key.constraints, key.contiguity);
+    fn for_each_solution(&self, mut f: impl FnMut(&[PartialCell]) -> ControlFlow<()>) {
+        let mut cells = self.row.cells.clone();
+        let _ = self.visit_solutions(0, 0, &mut cells, &mut f);
+    }
 
-    let mut num_solutions_cache = num_solutions_cache.write().unwrap();
-    num_solutions_cache.insert(key, solutions);
+    fn visit_solutions(
+        &self,
+        i: usize,
+        j: usize,
+        cells: &mut [PartialCell],
+        f: &mut impl FnMut(&[PartialCell]) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        if i >= cells.len() {
+            return f(cells);
+        }
 
-    solutions
-}
+        let (operational, damaged) = self.branches(i, j);
 
-fn compute_num_solutions(cells: &[PartialCell], constraints: &[u8], contiguity: Contiguity) -> u64 {
-    if constraints.is_empty() {
-        if cells.iter().all(|cell| *cell != PartialCell::Damaged) {
-            return 1;
-        } else {
-            return 0;
+        if operational > 0 {
+            cells[i] = PartialCell::Operational;
+            self.visit_solutions(i + 1, j, cells, f)?;
         }
-    }
 
-    match cells {
-        &[] => 0,
-        &[PartialCell::Operational, ref rest @ ..] => match contiguity {
-            Contiguity::ContinuesGroup => 0,
-            Contiguity::Normal | Contiguity::BreaksGroup => {
-                num_solutions(rest.into(), constraints.into(), Contiguity::Normal)
-            }
-        },
-        &[PartialCell::Damaged, ..] => {
-            match contiguity {
-                Contiguity::Normal | Contiguity::ContinuesGroup => {}
-                Contiguity::BreaksGroup => {
-                    return 0;
+        if let Some((group_end, count)) = damaged {
+            if count > 0 {
+                cells[i..group_end].fill(PartialCell::Damaged);
+                if let Some(cell) = cells.get_mut(group_end) {
+                    *cell = PartialCell::Operational;
                 }
+                self.visit_solutions(group_end + 1, j + 1, cells, f)?;
             }
+        }
 
-            let Some((constraint, rest_constraints)) = constraints.split_first() else {
-                return 0;
-            };
-            let damaged_split_index = cells
-                .iter()
-                .enumerate()
-                .find_map(|(n, cell)| {
-                    if *cell != PartialCell::Damaged {
-                        Some(n)
-                    } else {
-                        None
-                    }
-                })
-                .unwrap_or_else(|| cells.len());
-            let (damaged, rest) = cells.split_at(damaged_split_index);
-            let num_damaged: u8 = damaged.len().try_into().unwrap();
-
-            match num_damaged.cmp(constraint) {
-                std::cmp::Ordering::Less => {
-                    let contiguous_constraints = [constraint - num_damaged]
-                        .into_iter()
-                        .chain(rest_constraints.iter().copied())
-                        .collect();
-                    num_solutions(
-                        rest.into(),
-                        contiguous_constraints,
-                        Contiguity::ContinuesGroup,
-                    )
+        ControlFlow::Continue(())
+    }
+
+    /// Pick a solution uniformly at random, by taking each branch with a
+    /// probability proportional to the number of solutions under it.
+    fn sample(&self, rng: &mut impl rand::Rng) -> Vec<PartialCell> {
+        let mut cells = self.row.cells.clone();
+        let (mut i, mut j) = (0, 0);
+
+        while i < cells.len() {
+            let (operational, damaged) = self.branches(i, j);
+            let total = self.solutions[i][j];
+            if rng.gen_range(0..total) < operational {
+                cells[i] = PartialCell::Operational;
+                i += 1;
+            } else {
+                let (group_end, _) = damaged.expect("no branch to sample");
+                cells[i..group_end].fill(PartialCell::Damaged);
+                if let Some(cell) = cells.get_mut(group_end) {
+                    *cell = PartialCell::Operational;
                 }
-                std::cmp::Ordering::Equal => num_solutions(
-                    rest.into(),
-                    rest_constraints.into(),
-                    Contiguity::BreaksGroup,
-                ),
-                std::cmp::Ordering::Greater => 0,
+                i = group_end + 1;
+                j += 1;
             }
         }
-        &[PartialCell::Unknown, ref rest @ ..] => {
-            let a = vec![PartialCell::Damaged]
-                .into_iter()
-                .chain(rest.iter().copied())
-                .collect();
-            let a_solutions = num_solutions(a, constraints.into(), contiguity);
-            let b = [PartialCell::Operational]
-                .into_iter()
-                .chain(rest.iter().copied())
-                .collect();
-            let b_solutions = num_solutions(b, constraints.into(), contiguity);
-            a_solutions + b_solutions
-        }
+
+        cells
     }
 }
 
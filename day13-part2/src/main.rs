@@ -1,4 +1,17 @@
-use std::{collections::HashSet, io::Read as _};
+use std::io::Read as _;
+
+use clap::Parser as _;
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// How many smudges each map's reflection line must have
+    #[arg(long, default_value_t = 1)]
+    smudges: usize,
+
+    /// Print each map with its reflection line and smudges highlighted
+    #[arg(long)]
+    show: bool,
+}
 
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
@@ -11,17 +24,33 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
     let maps = parse_all(&input)?;
     let mut summary = 0;
-    for map in maps {
-        let unsmudged_reflections = map.unsmudged_reflections()?;
-        summary += unsmudged_reflections
+    for (n, map) in maps.iter().enumerate() {
+        let smudged_reflections = map.smudged_reflections(args.smudges);
+        eyre::ensure!(
+            !smudged_reflections.is_empty(),
+            "no reflection with {} smudge(s) found for map {}",
+            args.smudges,
+            n + 1,
+        );
+
+        if args.show {
+            for smudged_reflection in &smudged_reflections {
+                println!("map {}: {:?}", n + 1, smudged_reflection.reflection);
+                println!("{}", DisplaySmudgedMap(map, smudged_reflection));
+            }
+        }
+
+        summary += smudged_reflections
             .iter()
-            .map(|reflection| reflection.value())
+            .map(|smudged_reflection| smudged_reflection.reflection.value())
             .sum::<usize>();
     }
 
@@ -38,9 +67,11 @@ fn parse_all(s: &str) -> eyre::Result<Vec<Map>> {
     Ok(maps)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct Map {
     rows: Vec<Vec<Cell>>,
+    row_bits: Vec<Bits>,
+    col_bits: Vec<Bits>,
 }
 
 impl Map {
@@ -49,101 +80,128 @@ impl Map {
     }
 
     fn num_cols(&self) -> usize {
-        self.rows.get(0).map(|row| row.len()).unwrap_or(0)
+        self.rows.first().map(|row| row.len()).unwrap_or(0)
     }
 
-    fn is_vertical_reflection(&self, col: usize) -> bool {
-        let mut result = false;
-
-        let mut i = 0;
-        loop {
-            let Some(left) = col.checked_sub(i) else {
-                break;
-            };
-            let right = col + i + 1;
-            if right >= self.num_cols() {
-                break;
+    /// Compare each pair of columns mirrored across the line after `col`,
+    /// returning the mismatched cells, or `None` if there are more than
+    /// `max_smudges` mismatches.
+    fn vertical_smudges(&self, col: usize, max_smudges: usize) -> Option<Vec<Smudge>> {
+        let mut smudges = vec![];
+
+        let mirrored_cols = (0..=col).rev().zip((col + 1)..self.num_cols());
+        for (left, right) in mirrored_cols {
+            let left_bits = &self.col_bits[left];
+            let right_bits = &self.col_bits[right];
+            if smudges.len() + left_bits.num_mismatches(right_bits) > max_smudges {
+                return None;
             }
 
-            for row in &self.rows {
-                if row[left] != row[right] {
-                    return false;
-                }
+            smudges.extend(left_bits.mismatches(right_bits).map(|row| Smudge {
+                a: (row, left),
+                b: (row, right),
+            }));
+        }
+
+        Some(smudges)
+    }
+
+    /// Compare each pair of rows mirrored across the line after `row`,
+    /// returning the mismatched cells, or `None` if there are more than
+    /// `max_smudges` mismatches.
+    fn horizontal_smudges(&self, row: usize, max_smudges: usize) -> Option<Vec<Smudge>> {
+        let mut smudges = vec![];
+
+        let mirrored_rows = (0..=row).rev().zip((row + 1)..self.num_rows());
+        for (top, bottom) in mirrored_rows {
+            let top_bits = &self.row_bits[top];
+            let bottom_bits = &self.row_bits[bottom];
+            if smudges.len() + top_bits.num_mismatches(bottom_bits) > max_smudges {
+                return None;
             }
 
-            i += 1;
-            result = true;
+            smudges.extend(top_bits.mismatches(bottom_bits).map(|col| Smudge {
+                a: (top, col),
+                b: (bottom, col),
+            }));
         }
 
-        result
+        Some(smudges)
     }
 
-    fn is_horizontal_reflection(&self, row: usize) -> bool {
-        let mut result = false;
+    fn smudged_reflections(&self, num_smudges: usize) -> Vec<SmudgedReflection> {
+        let vertical_reflections = (0..self.num_cols().saturating_sub(1)).filter_map(|col| {
+            let smudges = self.vertical_smudges(col, num_smudges)?;
+            Some(SmudgedReflection {
+                reflection: Reflection::Vertical { col },
+                smudges,
+            })
+        });
+        let horizontal_reflections = (0..self.num_rows().saturating_sub(1)).filter_map(|row| {
+            let smudges = self.horizontal_smudges(row, num_smudges)?;
+            Some(SmudgedReflection {
+                reflection: Reflection::Horizontal { row },
+                smudges,
+            })
+        });
 
-        let mut i = 0;
-        loop {
-            let Some(left) = row.checked_sub(i) else {
-                break;
-            };
-            let right = row + i + 1;
-            if right >= self.num_rows() {
-                break;
-            }
+        vertical_reflections
+            .chain(horizontal_reflections)
+            .filter(|reflection| reflection.smudges.len() == num_smudges)
+            .collect()
+    }
+}
 
-            let left_row = &self.rows[left];
-            let right_row = &self.rows[right];
-            for (a, b) in left_row.iter().zip(right_row.iter()) {
-                if a != b {
-                    return false;
-                }
-            }
+/// A pair of cells on opposite sides of a reflection line that don't
+/// match. Flipping either cell removes the smudge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Smudge {
+    a: (usize, usize),
+    b: (usize, usize),
+}
 
-            i += 1;
-            result = true;
-        }
+#[derive(Debug, Clone)]
+struct SmudgedReflection {
+    reflection: Reflection,
+    smudges: Vec<Smudge>,
+}
 
-        result
-    }
+struct DisplaySmudgedMap<'a>(&'a Map, &'a SmudgedReflection);
 
-    fn unsmudged_reflections(&self) -> eyre::Result<Vec<Reflection>> {
-        let reflections = self.reflections().collect::<HashSet<_>>();
-        for row in 0..self.num_rows() {
-            for col in 0..self.num_cols() {
-                let mut candidate = self.clone();
-                candidate.rows[row][col].flip();
+impl std::fmt::Display for DisplaySmudgedMap<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self(map, smudged_reflection) = self;
+        let is_smudge = |row, col| {
+            smudged_reflection
+                .smudges
+                .iter()
+                .any(|smudge| smudge.a == (row, col) || smudge.b == (row, col))
+        };
 
-                let new_candidate_reflections = candidate
-                    .reflections()
-                    .filter(|reflection| !reflections.contains(reflection))
-                    .collect::<Vec<_>>();
+        if let Reflection::Vertical { col } = smudged_reflection.reflection {
+            writeln!(f, " {}><", " ".repeat(col))?;
+        }
 
-                if !new_candidate_reflections.is_empty() {
-                    return Ok(new_candidate_reflections);
+        for (row, cells) in map.rows.iter().enumerate() {
+            let marker = match smudged_reflection.reflection {
+                Reflection::Horizontal { row: line } if row == line => 'v',
+                Reflection::Horizontal { row: line } if row == line + 1 => '^',
+                _ => ' ',
+            };
+            write!(f, "{marker}")?;
+
+            for (col, cell) in cells.iter().enumerate() {
+                if is_smudge(row, col) {
+                    // Highlight smudges using reverse video
+                    write!(f, "\x1b[7m{cell}\x1b[0m")?;
+                } else {
+                    write!(f, "{cell}")?;
                 }
             }
+            writeln!(f)?;
         }
 
-        eyre::bail!("smudge not found for map");
-    }
-
-    fn reflections(&self) -> impl Iterator<Item = Reflection> + '_ {
-        let vertical_reflections = (0..self.num_cols()).filter_map(|col| {
-            if self.is_vertical_reflection(col) {
-                Some(Reflection::Vertical { col })
-            } else {
-                None
-            }
-        });
-        let horizontal_reflections = (0..self.num_rows()).filter_map(|row| {
-            if self.is_horizontal_reflection(row) {
-                Some(Reflection::Horizontal { row })
-            } else {
-                None
-            }
-        });
-
-        vertical_reflections.chain(horizontal_reflections)
+        Ok(())
     }
 }
 
@@ -175,27 +233,83 @@ impl std::str::FromStr for Map {
             })
             .collect::<eyre::Result<Vec<Vec<_>>>>()?;
 
-        Ok(Self { rows })
+        let num_cols = rows.first().map(|row| row.len()).unwrap_or(0);
+        eyre::ensure!(
+            rows.iter().all(|row| row.len() == num_cols),
+            "rows have different lengths"
+        );
+
+        let row_bits = rows
+            .iter()
+            .map(|row| Bits::from_cells(row.iter().copied()))
+            .collect();
+        let col_bits = (0..num_cols)
+            .map(|col| Bits::from_cells(rows.iter().map(|row| row[col])))
+            .collect();
+
+        Ok(Self {
+            rows,
+            row_bits,
+            col_bits,
+        })
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Cell {
-    Ash,
-    Rock,
+/// A row or column of cells packed into bits (rocks are set), so two rows
+/// or columns can be compared a word at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Bits {
+    words: Vec<u64>,
 }
 
-impl Cell {
-    fn flip(&mut self) {
-        match self {
-            Self::Ash => {
-                *self = Self::Rock;
+impl Bits {
+    fn from_cells(cells: impl Iterator<Item = Cell>) -> Self {
+        let mut words = vec![];
+        for (n, cell) in cells.enumerate() {
+            if n % 64 == 0 {
+                words.push(0);
             }
-            Self::Rock => {
-                *self = Self::Ash;
+            if cell == Cell::Rock {
+                *words.last_mut().unwrap() |= 1 << (n % 64);
             }
         }
+
+        Self { words }
     }
+
+    fn num_mismatches(&self, other: &Self) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the index of each cell that differs between `self` and `other`.
+    fn mismatches<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = usize> + 'a {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .enumerate()
+            .flat_map(|(word_index, (a, b))| {
+                let mut diff = a ^ b;
+                std::iter::from_fn(move || {
+                    if diff == 0 {
+                        return None;
+                    }
+
+                    let bit = diff.trailing_zeros() as usize;
+                    diff &= diff - 1;
+                    Some(word_index * 64 + bit)
+                })
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Ash,
+    Rock,
 }
 
 impl TryFrom<char> for Cell {
@@ -211,3 +325,12 @@ impl TryFrom<char> for Cell {
         }
     }
 }
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ash => write!(f, "."),
+            Self::Rock => write!(f, "#"),
+        }
+    }
+}
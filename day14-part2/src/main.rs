@@ -1,6 +1,22 @@
-use std::{collections::HashSet, io::Read as _};
-
-const CYCLES: usize = 1_000_000_000;
+use std::{
+    hash::{Hash, Hasher as _},
+    io::Read as _,
+    sync::Arc,
+};
+
+use clap::Parser as _;
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// How many spin cycles to run before measuring the load
+    #[arg(default_value_t = 1_000_000_000)]
+    cycles: usize,
+
+    /// The directions to tilt the platform in during each spin cycle, as a
+    /// string of `N`, `W`, `S`, and `E`
+    #[arg(long, default_value = "NWSE")]
+    spin: SpinCycle,
+}
 
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
@@ -13,144 +29,239 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
-    let mut platform: Platform = input.parse()?;
+    let platform: Platform = input.parse()?;
 
-    let mut initial_platform_states = HashSet::new();
-    loop {
-        let is_new_state = initial_platform_states.insert(platform.clone());
-        if !is_new_state {
-            break;
-        }
+    let spin = |platform: &mut Platform| platform.spin(&args.spin);
+
+    let cycle = find_cycle(&platform, spin);
+    tracing::info!(
+        pre_period = cycle.pre_period,
+        period = cycle.period,
+        "found cycle"
+    );
+
+    let platform = state_after(&platform, spin, &cycle, args.cycles);
+    let load_after_all_cycles = platform.total_load();
+
+    println!("{load_after_all_cycles}");
+
+    Ok(())
+}
 
-        platform.roll_cycle();
+/// Describes the sequence of states visited by repeatedly applying a step
+/// function: after `pre_period` steps, the states repeat every `period`
+/// steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cycle {
+    pre_period: usize,
+    period: usize,
+}
+
+/// A state along with its hash, so states can be compared by hash first
+/// and only fully compared when the hashes match.
+#[derive(Debug, Clone)]
+struct HashedState<T> {
+    state: T,
+    hash: u64,
+}
+
+impl<T: Hash + Eq> HashedState<T> {
+    fn new(state: T) -> Self {
+        let hash = hash_state(&state);
+        Self { state, hash }
+    }
+
+    fn step(&mut self, step: &mut impl FnMut(&mut T)) {
+        step(&mut self.state);
+        self.hash = hash_state(&self.state);
+    }
+}
+
+impl<T: Eq> PartialEq for HashedState<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.state == other.state
     }
+}
 
-    let mut cycle_platform_states = HashSet::new();
-    let mut cycle_loads = vec![];
-    loop {
-        let is_new_state = cycle_platform_states.insert(platform.clone());
-        if !is_new_state {
-            break;
+fn hash_state(state: &impl Hash) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Find the cycle reached by repeatedly applying `step` to `initial`,
+/// using Brent's algorithm. Only a few states are kept at once, so this
+/// works even when the pre-period or period is very long.
+fn find_cycle<T>(initial: &T, mut step: impl FnMut(&mut T)) -> Cycle
+where
+    T: Clone + Hash + Eq,
+{
+    // Find the period by moving the tortoise up to the hare at each power
+    // of two, until the hare steps back onto the tortoise
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = HashedState::new(initial.clone());
+    let mut hare = tortoise.clone();
+    hare.step(&mut step);
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
         }
 
-        cycle_loads.push(platform.total_load());
+        hare.step(&mut step);
+        period += 1;
+    }
 
-        platform.roll_cycle();
+    // Find the pre-period by starting the hare one period ahead of the
+    // tortoise, then stepping both until they meet
+    let mut tortoise = HashedState::new(initial.clone());
+    let mut hare = tortoise.clone();
+    for _ in 0..period {
+        hare.step(&mut step);
     }
 
-    let index_after_all_cycles = (CYCLES - initial_platform_states.len()) % cycle_loads.len();
-    let load_after_all_cycles = cycle_loads[index_after_all_cycles];
-    tracing::info!(
-        initial_states = initial_platform_states.len(),
-        cycle_states = cycle_platform_states.len(),
-        ?cycle_loads,
-        ?index_after_all_cycles,
-        "found cycle"
-    );
+    let mut pre_period = 0;
+    while tortoise != hare {
+        tortoise.step(&mut step);
+        hare.step(&mut step);
+        pre_period += 1;
+    }
 
-    println!("{load_after_all_cycles}");
+    Cycle { pre_period, period }
+}
 
-    Ok(())
+/// Get the state after applying `step` to `initial` `n` times, skipping
+/// over whole periods of `cycle`.
+fn state_after<T: Clone>(initial: &T, mut step: impl FnMut(&mut T), cycle: &Cycle, n: usize) -> T {
+    let num_steps = if n < cycle.pre_period {
+        n
+    } else {
+        cycle.pre_period + (n - cycle.pre_period) % cycle.period
+    };
+
+    let mut state = initial.clone();
+    for _ in 0..num_steps {
+        step(&mut state);
+    }
+
+    state
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The platform's cube rocks never move, so they're shared between every
+/// copy of the platform, and only the round rocks are stored per state.
+#[derive(Debug, Clone)]
 struct Platform {
-    rows: Vec<Vec<Cell>>,
+    num_rows: usize,
+    num_cols: usize,
+    cube_rocks: Arc<Bits>,
+    rocks: Bits,
 }
 
-impl Platform {
-    fn roll_cycle(&mut self) {
-        while self.roll_up() {}
-        while self.roll_left() {}
-        while self.roll_down() {}
-        while self.roll_right() {}
-    }
-
-    fn roll_up(&mut self) -> bool {
-        let mut moved = false;
-        for i in 1..self.rows.len() {
-            for j in 0..self.rows[i].len() {
-                match (self.rows[i - 1][j], self.rows[i][j]) {
-                    (Cell::Space, Cell::Rock) => {
-                        self.rows[i - 1][j] = Cell::Rock;
-                        self.rows[i][j] = Cell::Space;
-                        moved = true;
-                    }
-                    _ => {}
-                }
-            }
-        }
+impl PartialEq for Platform {
+    fn eq(&self, other: &Self) -> bool {
+        self.num_rows == other.num_rows
+            && self.num_cols == other.num_cols
+            && self.rocks == other.rocks
+            && (Arc::ptr_eq(&self.cube_rocks, &other.cube_rocks)
+                || self.cube_rocks == other.cube_rocks)
+    }
+}
 
-        moved
+impl Eq for Platform {}
+
+impl Hash for Platform {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.rocks.hash(state);
     }
+}
 
-    fn roll_down(&mut self) -> bool {
-        let mut moved = false;
-        for i in 0..self.rows.len().saturating_sub(1) {
-            for j in 0..self.rows[i].len() {
-                match (self.rows[i + 1][j], self.rows[i][j]) {
-                    (Cell::Space, Cell::Rock) => {
-                        self.rows[i + 1][j] = Cell::Rock;
-                        self.rows[i][j] = Cell::Space;
-                        moved = true;
-                    }
-                    _ => {}
-                }
-            }
+impl Platform {
+    fn index(&self, row: usize, col: usize) -> usize {
+        row * self.num_cols + col
+    }
+
+    fn cell(&self, row: usize, col: usize) -> Cell {
+        let index = self.index(row, col);
+        if self.cube_rocks.get(index) {
+            Cell::CubeRock
+        } else if self.rocks.get(index) {
+            Cell::Rock
+        } else {
+            Cell::Space
         }
+    }
 
-        moved
+    fn spin(&mut self, spin: &SpinCycle) {
+        for direction in &spin.tilts {
+            self.tilt(*direction);
+        }
     }
 
-    fn roll_left(&mut self) -> bool {
-        let mut moved = false;
-        for i in 0..self.rows.len() {
-            for j in 1..self.rows[i].len() {
-                match (self.rows[i][j - 1], self.rows[i][j]) {
-                    (Cell::Space, Cell::Rock) => {
-                        self.rows[i][j - 1] = Cell::Rock;
-                        self.rows[i][j] = Cell::Space;
-                        moved = true;
-                    }
-                    _ => {}
+    fn tilt(&mut self, direction: Direction) {
+        let num_rows = self.num_rows;
+        let num_cols = self.num_cols;
+
+        match direction {
+            Direction::North => {
+                for col in 0..num_cols {
+                    self.tilt_line(num_rows, |n| (n, col));
+                }
+            }
+            Direction::South => {
+                for col in 0..num_cols {
+                    self.tilt_line(num_rows, |n| (num_rows - 1 - n, col));
+                }
+            }
+            Direction::West => {
+                for row in 0..num_rows {
+                    self.tilt_line(num_cols, |n| (row, n));
+                }
+            }
+            Direction::East => {
+                for row in 0..num_rows {
+                    self.tilt_line(num_cols, |n| (row, num_cols - 1 - n));
                 }
             }
         }
-
-        moved
     }
 
-    fn roll_right(&mut self) -> bool {
-        let mut moved = false;
-        for i in 0..self.rows.len() {
-            for j in 0..self.rows[i].len().saturating_sub(1) {
-                match (self.rows[i][j + 1], self.rows[i][j]) {
-                    (Cell::Space, Cell::Rock) => {
-                        self.rows[i][j + 1] = Cell::Rock;
-                        self.rows[i][j] = Cell::Space;
-                        moved = true;
-                    }
-                    _ => {}
+    /// Roll every rock in a line of cells towards the start of the line in
+    /// a single pass. `position` maps an index along the line to a row and
+    /// column, and the first free slot is tracked as we go: it moves just
+    /// past each cube rock, and each rolled rock fills it.
+    fn tilt_line(&mut self, len: usize, position: impl Fn(usize) -> (usize, usize)) {
+        let mut free = 0;
+        for n in 0..len {
+            let (row, col) = position(n);
+            match self.cell(row, col) {
+                Cell::Space => {}
+                Cell::CubeRock => {
+                    free = n + 1;
+                }
+                Cell::Rock => {
+                    let (free_row, free_col) = position(free);
+                    let index = self.index(row, col);
+                    let free_index = self.index(free_row, free_col);
+                    self.rocks.set(index, false);
+                    self.rocks.set(free_index, true);
+                    free += 1;
                 }
             }
         }
-
-        moved
     }
 
     fn total_load(&self) -> usize {
-        let num_rows = self.rows.len();
-        self.rows
-            .iter()
-            .enumerate()
-            .map(|(n, row)| {
-                let num_rocks = row.iter().filter(|cell| **cell == Cell::Rock).count();
-                num_rocks * (num_rows - n)
-            })
+        self.rocks
+            .ones()
+            .map(|index| self.num_rows - index / self.num_cols)
             .sum()
     }
 }
@@ -166,20 +277,43 @@ impl std::str::FromStr for Platform {
                     .map(Cell::try_from)
                     .collect::<eyre::Result<Vec<_>>>()
             })
-            .collect::<eyre::Result<_>>()?;
-        Ok(Self { rows })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        let num_rows = rows.len();
+        let num_cols = rows.first().map(|row| row.len()).unwrap_or(0);
+        eyre::ensure!(
+            rows.iter().all(|row| row.len() == num_cols),
+            "rows have different lengths"
+        );
+
+        let mut cube_rocks = Bits::new(num_rows * num_cols);
+        let mut rocks = Bits::new(num_rows * num_cols);
+        for (index, cell) in rows.into_iter().flatten().enumerate() {
+            match cell {
+                Cell::Space => {}
+                Cell::CubeRock => cube_rocks.set(index, true),
+                Cell::Rock => rocks.set(index, true),
+            }
+        }
+
+        Ok(Self {
+            num_rows,
+            num_cols,
+            cube_rocks: Arc::new(cube_rocks),
+            rocks,
+        })
     }
 }
 
 impl std::fmt::Display for Platform {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (n, row) in self.rows.iter().enumerate() {
-            if n != 0 {
+        for row in 0..self.num_rows {
+            if row != 0 {
                 writeln!(f)?;
             }
 
-            for cell in row {
-                write!(f, "{cell}")?;
+            for col in 0..self.num_cols {
+                write!(f, "{}", self.cell(row, col))?;
             }
         }
 
@@ -187,7 +321,96 @@ impl std::fmt::Display for Platform {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A fixed-size set of bits, one per cell of the platform.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Bits {
+    words: Vec<u64>,
+}
+
+impl Bits {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    fn get(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn set(&mut self, index: usize, value: bool) {
+        let word = &mut self.words[index / 64];
+        if value {
+            *word |= 1 << (index % 64);
+        } else {
+            *word &= !(1 << (index % 64));
+        }
+    }
+
+    /// Returns the index of each set bit, in order.
+    fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(|(word_index, word)| {
+                let mut word = *word;
+                std::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(word_index * 64 + bit)
+                })
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SpinCycle {
+    tilts: Vec<Direction>,
+}
+
+impl std::str::FromStr for SpinCycle {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tilts = s
+            .chars()
+            .map(Direction::try_from)
+            .collect::<eyre::Result<Vec<_>>>()?;
+        eyre::ensure!(!tilts.is_empty(), "spin cycle must tilt at least once");
+
+        Ok(Self { tilts })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    North,
+    West,
+    South,
+    East,
+}
+
+impl TryFrom<char> for Direction {
+    type Error = eyre::Error;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        match value {
+            'N' => Ok(Self::North),
+            'W' => Ok(Self::West),
+            'S' => Ok(Self::South),
+            'E' => Ok(Self::East),
+            other => {
+                eyre::bail!("invalid direction: {other:?}");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Cell {
     Space,
     CubeRock,
@@ -1,6 +1,31 @@
-use std::io::Read as _;
+use std::{hash::Hash, io::Read as _};
 
-use eyre::OptionExt;
+use clap::Parser as _;
+use eyre::{OptionExt, WrapErr as _};
+use indexmap::{Equivalent, IndexMap};
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// Print the contents of each non-empty box after every step
+    #[arg(long)]
+    trace: bool,
+
+    /// Stop after applying this many steps
+    #[arg(long)]
+    until: Option<usize>,
+
+    /// The value to multiply by after adding each character of a label
+    #[arg(long, default_value_t = 17)]
+    multiplier: u64,
+
+    /// The value to take the remainder of after each multiplication
+    #[arg(long, default_value_t = 256)]
+    modulus: u64,
+
+    /// How many boxes to sort lenses into
+    #[arg(long, default_value_t = 256)]
+    boxes: usize,
+}
 
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
@@ -13,54 +38,126 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
     let steps = input.lines().next().ok_or_eyre("no steps")?;
-    let steps = steps.split(",").map(Step::parse);
+    let steps = steps.split(",").take(args.until.unwrap_or(usize::MAX));
+
+    let hasher = Hasher::new(args.multiplier, args.modulus)?;
+    let mut lenses = Hashmap::new(hasher, args.boxes)?;
 
-    let mut boxes = vec![LensBox::default(); 256];
+    for (n, raw_step) in steps.enumerate() {
+        let step_result = apply_step(&mut lenses, raw_step, args.trace);
+        step_result.wrap_err_with(|| format!("failed to apply step {} ({raw_step:?})", n + 1))?;
 
-    for step in steps {
-        let step = step?;
-        let hash = hash(step.label());
-        let hash: usize = hash.try_into().expect("invalid hash");
-        boxes[hash].apply(&step);
+        if args.trace {
+            println!("After {raw_step:?}:");
+            println!("{lenses}");
+        }
     }
 
-    let total_focusing_power: u64 = boxes
-        .iter()
-        .enumerate()
-        .flat_map(|(i, box_)| {
-            box_.lenses
-                .iter()
-                .enumerate()
-                .map(move |(j, (_, value))| -> u64 {
-                    let i: u64 = i.try_into().unwrap();
-                    let j: u64 = j.try_into().unwrap();
-                    let value: u64 = (*value).into();
-                    (i + 1) * (j + 1) * value
-                })
-        })
-        .sum();
+    let total_focusing_power = focusing_power(&lenses);
 
     println!("{total_focusing_power}");
 
     Ok(())
 }
 
-fn hash(s: &str) -> u64 {
-    let mut value = 0;
-    for c in s.chars() {
-        let ascii: u8 = c.try_into().expect("invalid ASCII");
-        let ascii: u64 = ascii.into();
-        value += ascii;
-        value *= 17;
-        value %= 256;
+fn focusing_power(lenses: &Hashmap<&str, u8>) -> u64 {
+    lenses
+        .boxes()
+        .flat_map(|(i, lens_box)| {
+            lens_box.enumerate().map(move |(j, (_, value))| -> u64 {
+                let i: u64 = i.try_into().unwrap();
+                let j: u64 = j.try_into().unwrap();
+                let value: u64 = (*value).into();
+                (i + 1) * (j + 1) * value
+            })
+        })
+        .sum()
+}
+
+fn apply_step<'a>(
+    lenses: &mut Hashmap<&'a str, u8>,
+    raw_step: &'a str,
+    trace: bool,
+) -> eyre::Result<()> {
+    let step = Step::parse(raw_step)?;
+
+    if trace {
+        if let Step::Add { label, .. } = step {
+            let colliding_labels = lenses.colliding_keys(label)?.collect::<Vec<_>>();
+            if !colliding_labels.is_empty() {
+                tracing::warn!(
+                    "label {label:?} hashes into box {} with {colliding_labels:?}",
+                    lenses.box_index(label)?,
+                );
+            }
+        }
+    }
+
+    step.apply(lenses)?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Hasher {
+    multiplier: u64,
+    modulus: u64,
+}
+
+impl Hasher {
+    fn new(multiplier: u64, modulus: u64) -> eyre::Result<Self> {
+        eyre::ensure!(modulus > 0, "modulus must be at least 1");
+        Ok(Self {
+            multiplier,
+            modulus,
+        })
+    }
+
+    fn hash(&self, s: &str) -> eyre::Result<u64> {
+        let multiplier = u128::from(self.multiplier);
+        let modulus = u128::from(self.modulus);
+
+        let mut value = 0;
+        for (n, c) in s.char_indices() {
+            eyre::ensure!(
+                c.is_ascii(),
+                "invalid non-ASCII character {c:?} at byte {n} of {s:?}"
+            );
+            let ascii = u128::from(c as u8);
+
+            // Reduce before multiplying so large multipliers and moduli
+            // can't overflow
+            value = (value + ascii) % modulus;
+            value = (value * multiplier) % modulus;
+        }
+
+        Ok(value.try_into().unwrap())
     }
+}
+
+impl<K, V> std::fmt::Display for Hashmap<K, V>
+where
+    K: AsRef<str> + Hash + Eq,
+    V: std::fmt::Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, entries) in self.boxes() {
+            write!(f, "Box {i}:")?;
+            for (key, value) in entries {
+                write!(f, " [{} {value}]", key.as_ref())?;
+            }
+            writeln!(f)?;
+        }
 
-    value
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -81,46 +178,92 @@ impl<'a> Step<'a> {
         }
     }
 
-    fn label(&self) -> &str {
+    fn apply(&self, lenses: &mut Hashmap<&'a str, u8>) -> eyre::Result<()> {
         match self {
-            Step::Add { label, value: _ } => label,
-            Step::Remove { label } => label,
+            Step::Add { label, value } => {
+                lenses.insert(label, *value)?;
+            }
+            Step::Remove { label } => {
+                lenses.remove(label)?;
+            }
         }
+
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone, Default)]
-struct LensBox<'a> {
-    lenses: Vec<(&'a str, u8)>,
+/// The Holiday ASCII String Helper Manual Arrangement Procedure: a hash
+/// map where each key is sorted into a box using a [`Hasher`], and each box
+/// keeps its entries in insertion order. Replacing an entry's value keeps its place in the box.
+#[derive(Debug, Clone)]
+struct Hashmap<K, V> {
+    hasher: Hasher,
+    boxes: Vec<IndexMap<K, V>>,
 }
 
-impl<'a> LensBox<'a> {
-    fn apply(&mut self, step: &Step<'a>) {
-        match step {
-            Step::Add { label, value } => {
-                if let Some(index) = self.lens_index(label) {
-                    self.lenses[index] = (label, *value);
-                } else {
-                    self.lenses.push((label, *value));
-                }
-            }
-            Step::Remove { label } => {
-                if let Some(lens_index) = self.lens_index(label) {
-                    self.lenses.remove(lens_index);
-                }
-            }
-        }
+impl<K, V> Hashmap<K, V>
+where
+    K: AsRef<str> + Hash + Eq,
+{
+    fn new(hasher: Hasher, num_boxes: usize) -> eyre::Result<Self> {
+        eyre::ensure!(num_boxes > 0, "need at least 1 box");
+        Ok(Self {
+            hasher,
+            boxes: (0..num_boxes).map(|_| IndexMap::new()).collect(),
+        })
     }
 
-    fn lens_index(&self, label: &str) -> Option<usize> {
-        self.lenses.iter().enumerate().find_map(
-            |(i, (lens_label, _))| {
-                if *lens_label == label {
-                    Some(i)
-                } else {
-                    None
-                }
-            },
-        )
+    fn box_index(&self, key: &(impl AsRef<str> + ?Sized)) -> eyre::Result<usize> {
+        let hash = self.hasher.hash(key.as_ref())?;
+        let num_boxes: u64 = self.boxes.len().try_into()?;
+        Ok((hash % num_boxes).try_into()?)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> eyre::Result<Option<V>> {
+        let box_index = self.box_index(&key)?;
+        Ok(self.boxes[box_index].insert(key, value))
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> eyre::Result<Option<V>>
+    where
+        Q: AsRef<str> + Hash + Equivalent<K> + ?Sized,
+    {
+        let box_index = self.box_index(key)?;
+        Ok(self.boxes[box_index].shift_remove(key))
+    }
+
+    #[allow(unused)]
+    fn get<Q>(&self, key: &Q) -> eyre::Result<Option<&V>>
+    where
+        Q: AsRef<str> + Hash + Equivalent<K> + ?Sized,
+    {
+        let box_index = self.box_index(key)?;
+        Ok(self.boxes[box_index].get(key))
+    }
+
+    /// Returns the entries in each non-empty box, along with the box's
+    /// index.
+    fn boxes(&self) -> impl Iterator<Item = (usize, indexmap::map::Iter<'_, K, V>)> {
+        self.boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, entries)| !entries.is_empty())
+            .map(|(i, entries)| (i, entries.iter()))
+    }
+
+    /// Returns the other keys in the same box that `key` hashes into.
+    fn colliding_keys<'a, Q>(&'a self, key: &'a Q) -> eyre::Result<impl Iterator<Item = &'a K> + 'a>
+    where
+        Q: AsRef<str> + Hash + Equivalent<K> + ?Sized,
+    {
+        let box_index = self.box_index(key)?;
+        Ok(self.boxes[box_index]
+            .keys()
+            .filter(move |other| !key.equivalent(other)))
+    }
+
+    #[allow(unused)]
+    fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.boxes.iter().flat_map(|entries| entries.iter())
     }
 }
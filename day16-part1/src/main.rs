@@ -1,5 +1,39 @@
 use std::io::Read as _;
 
+use clap::Parser as _;
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// The row the beam enters the grid at
+    #[arg(long, default_value_t = 0)]
+    start_row: usize,
+
+    /// The column the beam enters the grid at
+    #[arg(long, default_value_t = 0)]
+    start_col: usize,
+
+    /// The direction the beam is travelling when it enters the grid
+    #[arg(long, value_enum, default_value_t = Direction::Right)]
+    direction: Direction,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Print the number of energized tiles (default)
+    Energize,
+
+    /// Try placing each kind of mirror or splitter in every empty tile, and
+    /// print the placements that energize the most and fewest tiles
+    WhatIf {
+        /// How many of the best and worst placements to print
+        #[arg(long, default_value_t = 1)]
+        top: usize,
+    },
+}
+
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -11,15 +45,72 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
-    let mut grid = Grid::parse_new(&input)?;
-    grid.energize(Position { row: 0, col: 0 }, Direction::Right);
-    let num_energized = grid.num_energized();
+    let grid = Grid::parse_new(&input)?;
+    let start = Position {
+        row: args.start_row,
+        col: args.start_col,
+    };
+    eyre::ensure!(
+        start.row < grid.num_rows() && start.col < grid.num_cols(),
+        "start {start:?} is outside the grid"
+    );
+
+    let mut energized_grid = grid.clone();
+    energized_grid.energize(start, args.direction);
+    let num_energized = energized_grid.num_energized();
+
+    match args.command.unwrap_or(Command::Energize) {
+        Command::Energize => {
+            println!("{num_energized}");
+        }
+        Command::WhatIf { top } => {
+            // Only tiles the beam already reaches can change the result, so
+            // every other placement energizes the same tiles as before
+            let mut placements = vec![];
+            for (row, cells) in energized_grid.rows.iter().enumerate() {
+                for (col, cell) in cells.iter().enumerate() {
+                    if !matches!(cell.contraption, Contraption::Empty)
+                        || !cell.energization.is_energized()
+                    {
+                        continue;
+                    }
+
+                    for contraption in Contraption::PLACEABLE {
+                        let mut candidate = grid.clone();
+                        candidate.rows[row][col].contraption = contraption;
+                        candidate.energize(start, args.direction);
+
+                        let position = Position { row, col };
+                        placements.push((candidate.num_energized(), contraption, position));
+                    }
+                }
+            }
+
+            placements.sort_by_key(|(num_energized, _, _)| std::cmp::Reverse(*num_energized));
 
-    println!("{num_energized}");
+            println!("without placement: {num_energized}");
+            println!("most energized:");
+            for (num_energized, contraption, position) in placements.iter().take(top) {
+                println!(
+                    "  {num_energized}: {contraption} at row {}, col {}",
+                    position.row, position.col
+                );
+            }
+            println!("fewest energized:");
+            for (num_energized, contraption, position) in placements.iter().rev().take(top) {
+                println!(
+                    "  {num_energized}: {contraption} at row {}, col {}",
+                    position.row, position.col
+                );
+            }
+        }
+    }
 
     Ok(())
 }
@@ -50,7 +141,7 @@ impl Grid {
     }
 
     fn num_cols(&self) -> usize {
-        self.rows.get(0).map(|row| row.len()).unwrap_or(0)
+        self.rows.first().map(|row| row.len()).unwrap_or(0)
     }
 
     fn num_energized(&self) -> usize {
@@ -65,13 +156,18 @@ impl Grid {
     }
 
     fn energize(&mut self, position: Position, direction: Direction) {
-        let next_directions = self.rows[position.row][position.col].energize(direction);
-        for next_direction in &next_directions {
-            let Some(next_pos) = self.move_position(position, *next_direction) else {
-                continue;
-            };
+        // Beams still to trace, kept in a queue instead of recursing so long
+        // beam paths can't overflow the stack
+        let mut beams = vec![(position, direction)];
+        while let Some((position, direction)) = beams.pop() {
+            let next_directions = self.rows[position.row][position.col].energize(direction);
+            for next_direction in next_directions {
+                let Some(next_pos) = self.move_position(position, next_direction) else {
+                    continue;
+                };
 
-            self.energize(next_pos, *next_direction);
+                beams.push((next_pos, next_direction));
+            }
         }
     }
 
@@ -159,6 +255,15 @@ enum Contraption {
     HorizontalSplitter,
 }
 
+impl Contraption {
+    const PLACEABLE: [Self; 4] = [
+        Self::ForwardMirror,
+        Self::BackwardMirror,
+        Self::VerticalSplitter,
+        Self::HorizontalSplitter,
+    ];
+}
+
 impl TryFrom<char> for Contraption {
     type Error = eyre::Error;
 
@@ -176,6 +281,20 @@ impl TryFrom<char> for Contraption {
     }
 }
 
+impl std::fmt::Display for Contraption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let c = match self {
+            Self::Empty => '.',
+            Self::ForwardMirror => '/',
+            Self::BackwardMirror => '\\',
+            Self::VerticalSplitter => '|',
+            Self::HorizontalSplitter => '-',
+        };
+
+        write!(f, "{c}")
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 struct Energization {
     upward: bool,
@@ -201,7 +320,7 @@ impl Energization {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
 enum Direction {
     Up,
     Right,
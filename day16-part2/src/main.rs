@@ -1,4 +1,4 @@
-use std::io::Read as _;
+use std::{collections::HashMap, io::Read as _};
 
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
@@ -41,16 +41,32 @@ fn main() -> eyre::Result<()> {
     let starts = top_edge_starts
         .chain(right_edge_starts)
         .chain(bottom_edge_starts)
-        .chain(left_edge_starts);
+        .chain(left_edge_starts)
+        .collect::<Vec<_>>();
 
-    let most_energy = starts
-        .map(|(pos, dir)| {
-            let mut grid = grid.clone();
-            grid.energize(pos, dir);
-            grid.num_energized()
-        })
-        .max()
-        .unwrap_or(0);
+    let beam_graph = BeamGraph::new(&grid, &starts);
+    tracing::debug!(
+        num_states = beam_graph.num_states(),
+        num_components = beam_graph.num_components(),
+        "built beam graph"
+    );
+
+    let best_start = starts
+        .into_iter()
+        .max_by_key(|(pos, dir)| beam_graph.num_energized(*pos, *dir));
+    let Some((best_pos, best_dir)) = best_start else {
+        println!("0");
+        return Ok(());
+    };
+    let most_energy = beam_graph.num_energized(best_pos, best_dir);
+
+    // Double-check the best start against a full simulation
+    let mut best_grid = grid.clone();
+    best_grid.energize(best_pos, best_dir);
+    eyre::ensure!(
+        best_grid.num_energized() == most_energy,
+        "beam graph disagrees with simulation for {best_pos:?} {best_dir:?}"
+    );
 
     println!("{most_energy}");
 
@@ -83,7 +99,7 @@ impl Grid {
     }
 
     fn num_cols(&self) -> usize {
-        self.rows.get(0).map(|row| row.len()).unwrap_or(0)
+        self.rows.first().map(|row| row.len()).unwrap_or(0)
     }
 
     fn num_energized(&self) -> usize {
@@ -98,13 +114,18 @@ impl Grid {
     }
 
     fn energize(&mut self, position: Position, direction: Direction) {
-        let next_directions = self.rows[position.row][position.col].energize(direction);
-        for next_direction in &next_directions {
-            let Some(next_pos) = self.move_position(position, *next_direction) else {
-                continue;
-            };
+        // Beams still to trace, kept in a queue instead of recursing so long
+        // beam paths can't overflow the stack
+        let mut beams = vec![(position, direction)];
+        while let Some((position, direction)) = beams.pop() {
+            let next_directions = self.rows[position.row][position.col].energize(direction);
+            for next_direction in next_directions {
+                let Some(next_pos) = self.move_position(position, next_direction) else {
+                    continue;
+                };
 
-            self.energize(next_pos, *next_direction);
+                beams.push((next_pos, next_direction));
+            }
         }
     }
 
@@ -136,6 +157,292 @@ impl Grid {
     }
 }
 
+/// Every beam state (a position along with the direction the beam is
+/// travelling when it enters that position) reachable from a set of
+/// starts, compiled into a graph. Cycles of states are condensed into
+/// strongly connected components, and each component's energized cells are
+/// built from its successors' cells, so every start can be answered by
+/// looking up its component.
+struct BeamGraph {
+    num_cols: usize,
+    state_components: Vec<usize>,
+    component_counts: Vec<usize>,
+}
+
+impl BeamGraph {
+    fn new(grid: &Grid, starts: &[(Position, Direction)]) -> Self {
+        let num_cols = grid.num_cols();
+        let num_cells = grid.num_rows() * num_cols;
+        let num_states = num_cells * 4;
+
+        let next_states = |state: usize| -> Vec<usize> {
+            let cell = state / 4;
+            let position = Position {
+                row: cell / num_cols,
+                col: cell % num_cols,
+            };
+            let direction = Direction::ALL[state % 4];
+            let contraption = grid.rows[position.row][position.col].contraption;
+            contraption
+                .beam_directions(direction)
+                .into_iter()
+                .filter_map(|next_direction| {
+                    let next_position = grid.move_position(position, next_direction)?;
+                    Some(state_index(num_cols, next_position, next_direction))
+                })
+                .collect()
+        };
+        let start_states = starts
+            .iter()
+            .map(|(position, direction)| state_index(num_cols, *position, *direction))
+            .collect::<Vec<_>>();
+
+        // Count the edges into each reachable state, so we know how many
+        // times each component's energized cells will be needed
+        let mut in_degrees = vec![0; num_states];
+        let mut reachable = vec![false; num_states];
+        let mut queue = vec![];
+        for state in &start_states {
+            if !std::mem::replace(&mut reachable[*state], true) {
+                queue.push(*state);
+            }
+        }
+        while let Some(state) = queue.pop() {
+            for next_state in next_states(state) {
+                in_degrees[next_state] += 1;
+                if !std::mem::replace(&mut reachable[next_state], true) {
+                    queue.push(next_state);
+                }
+            }
+        }
+
+        let mut state_components = vec![usize::MAX; num_states];
+        let mut component_counts = vec![];
+
+        // Energized cells for each component that still has predecessors
+        // left to build, along with how many edges still need them. Once
+        // the last edge uses a component's cells, they're handed off
+        // instead of copied, so long chains of states don't copy anything
+        let mut pending_energized: HashMap<usize, (usize, Bits)> = HashMap::new();
+
+        // Tarjan's algorithm returns components in reverse topological
+        // order, so each component's successors are always finished first
+        let components = StronglyConnectedComponents::new(num_states, start_states, next_states);
+        for states in components {
+            let component = component_counts.len();
+            for state in &states {
+                state_components[*state] = component;
+            }
+
+            let mut uses: usize = states.iter().map(|state| in_degrees[*state]).sum();
+            let mut energized: Option<(Bits, Option<usize>)> = None;
+            for state in &states {
+                for next_state in next_states(*state) {
+                    let next_component = state_components[next_state];
+                    if next_component == component {
+                        uses -= 1;
+                        continue;
+                    }
+
+                    let (remaining_uses, next_energized) = pending_energized
+                        .get_mut(&next_component)
+                        .expect("successor component not finished");
+                    *remaining_uses -= 1;
+                    let next_energized = if *remaining_uses == 0 {
+                        let (_, next_energized) =
+                            pending_energized.remove(&next_component).unwrap();
+                        next_energized
+                    } else {
+                        next_energized.clone()
+                    };
+
+                    energized = match energized {
+                        None => Some((next_energized, Some(component_counts[next_component]))),
+                        Some((mut energized, _)) => {
+                            energized.union_with(&next_energized);
+                            Some((energized, None))
+                        }
+                    };
+                }
+            }
+
+            // Only recount every cell when multiple successors were merged
+            let (mut energized, known_count) =
+                energized.unwrap_or_else(|| (Bits::new(num_cells), Some(0)));
+            let mut count = known_count;
+            for state in &states {
+                let is_new = energized.set(state / 4);
+                if let Some(count) = &mut count {
+                    *count += usize::from(is_new);
+                }
+            }
+            let count = count.unwrap_or_else(|| energized.count());
+
+            component_counts.push(count);
+            if uses > 0 {
+                pending_energized.insert(component, (uses, energized));
+            }
+        }
+
+        Self {
+            num_cols,
+            state_components,
+            component_counts,
+        }
+    }
+
+    fn num_states(&self) -> usize {
+        self.state_components.len()
+    }
+
+    fn num_components(&self) -> usize {
+        self.component_counts.len()
+    }
+
+    fn num_energized(&self, position: Position, direction: Direction) -> usize {
+        let state = state_index(self.num_cols, position, direction);
+        let component = self.state_components[state];
+        self.component_counts[component]
+    }
+}
+
+fn state_index(num_cols: usize, position: Position, direction: Direction) -> usize {
+    (position.row * num_cols + position.col) * 4 + direction.index()
+}
+
+/// Iterates over the strongly connected components of a graph reachable
+/// from `roots` using Tarjan's algorithm, yielding the components in
+/// reverse topological order. Uses an explicit stack, since beam paths can
+/// be far longer than the call stack allows.
+struct StronglyConnectedComponents<F> {
+    successors: F,
+    roots: std::vec::IntoIter<usize>,
+    index: Vec<usize>,
+    low_link: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+
+    /// Each frame is a node along with its successors that haven't been
+    /// followed yet
+    call_stack: Vec<(usize, std::vec::IntoIter<usize>)>,
+    next_index: usize,
+}
+
+impl<F> StronglyConnectedComponents<F>
+where
+    F: Fn(usize) -> Vec<usize>,
+{
+    const UNVISITED: usize = usize::MAX;
+
+    fn new(num_nodes: usize, roots: Vec<usize>, successors: F) -> Self {
+        Self {
+            successors,
+            roots: roots.into_iter(),
+            index: vec![Self::UNVISITED; num_nodes],
+            low_link: vec![0; num_nodes],
+            on_stack: vec![false; num_nodes],
+            stack: vec![],
+            call_stack: vec![],
+            next_index: 0,
+        }
+    }
+
+    fn visit(&mut self, node: usize) {
+        self.index[node] = self.next_index;
+        self.low_link[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+        self.call_stack
+            .push((node, (self.successors)(node).into_iter()));
+    }
+}
+
+impl<F> Iterator for StronglyConnectedComponents<F>
+where
+    F: Fn(usize) -> Vec<usize>,
+{
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((node, successors)) = self.call_stack.last_mut() else {
+                let root = self
+                    .roots
+                    .find(|root| self.index[*root] == Self::UNVISITED)?;
+                self.visit(root);
+                continue;
+            };
+            let node = *node;
+
+            if let Some(next) = successors.next() {
+                if self.index[next] == Self::UNVISITED {
+                    self.visit(next);
+                } else if self.on_stack[next] {
+                    self.low_link[node] = self.low_link[node].min(self.index[next]);
+                }
+
+                continue;
+            }
+
+            self.call_stack.pop();
+            if let Some((parent, _)) = self.call_stack.last() {
+                self.low_link[*parent] = self.low_link[*parent].min(self.low_link[node]);
+            }
+
+            if self.low_link[node] == self.index[node] {
+                let mut component = vec![];
+                loop {
+                    let member = self.stack.pop().expect("node missing from stack");
+                    self.on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+
+                return Some(component);
+            }
+        }
+    }
+}
+
+/// A fixed-size set of bits, one per cell of the grid.
+#[derive(Debug, Clone)]
+struct Bits {
+    words: Vec<u64>,
+}
+
+impl Bits {
+    fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    /// Sets a bit, returning `true` if it wasn't already set.
+    fn set(&mut self, index: usize) -> bool {
+        let word = &mut self.words[index / 64];
+        let bit = 1 << (index % 64);
+        let is_new = *word & bit == 0;
+        *word |= bit;
+        is_new
+    }
+
+    fn union_with(&mut self, other: &Self) {
+        for (word, other_word) in self.words.iter_mut().zip(&other.words) {
+            *word |= other_word;
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct GridCell {
     contraption: Contraption,
@@ -157,7 +464,24 @@ impl GridCell {
             return vec![];
         }
 
-        match (self.contraption, direction) {
+        self.contraption.beam_directions(direction)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Contraption {
+    Empty,
+    ForwardMirror,
+    BackwardMirror,
+    VerticalSplitter,
+    HorizontalSplitter,
+}
+
+impl Contraption {
+    /// Returns the directions of the beams leaving a cell with this
+    /// contraption, when a beam enters it travelling in `direction`.
+    fn beam_directions(&self, direction: Direction) -> Vec<Direction> {
+        match (self, direction) {
             (Contraption::Empty, direction) => vec![direction],
             (Contraption::ForwardMirror, Direction::Up) => vec![Direction::Right],
             (Contraption::ForwardMirror, Direction::Right) => vec![Direction::Up],
@@ -183,15 +507,6 @@ impl GridCell {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Contraption {
-    Empty,
-    ForwardMirror,
-    BackwardMirror,
-    VerticalSplitter,
-    HorizontalSplitter,
-}
-
 impl TryFrom<char> for Contraption {
     type Error = eyre::Error;
 
@@ -242,6 +557,19 @@ enum Direction {
     Left,
 }
 
+impl Direction {
+    const ALL: [Self; 4] = [Self::Up, Self::Right, Self::Down, Self::Left];
+
+    fn index(&self) -> usize {
+        match self {
+            Self::Up => 0,
+            Self::Right => 1,
+            Self::Down => 2,
+            Self::Left => 3,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct Position {
     row: usize,
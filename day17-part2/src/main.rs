@@ -1,7 +1,55 @@
-use std::io::Read as _;
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read as _,
+};
 
+use clap::Parser as _;
 use eyre::OptionExt as _;
-use pathfinding::directed::dijkstra::dijkstra;
+use pathfinding::directed::{astar::astar, dijkstra::dijkstra};
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// The fewest tiles the crucible must move in a straight line before it
+    /// can turn or stop
+    #[arg(long, default_value_t = 4)]
+    min_run: usize,
+
+    /// The most tiles the crucible can move in a straight line before it
+    /// has to turn
+    #[arg(long, default_value_t = 10)]
+    max_run: usize,
+
+    /// The search algorithm used to find the path with the least heat loss
+    #[arg(long, value_enum, default_value_t = Algorithm::Dijkstra)]
+    algorithm: Algorithm,
+
+    /// Print the chosen path over the grid, along with the heat lost at
+    /// each step
+    #[arg(long)]
+    show_path: bool,
+
+    /// The tile the crucible starts at, as `row,col` [default: top-left]
+    #[arg(long)]
+    start: Option<Position>,
+
+    /// The tile the crucible needs to reach, as `row,col`
+    /// [default: bottom-right]
+    #[arg(long)]
+    goal: Option<Position>,
+
+    /// A tile the crucible can't enter, as `row,col` (can be repeated)
+    #[arg(long)]
+    forbid: Vec<Position>,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum Algorithm {
+    Dijkstra,
+
+    /// A* search, using the Manhattan distance to the goal times the lowest
+    /// heat loss of any tile as the heuristic
+    Astar,
+}
 
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
@@ -14,33 +62,86 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+    let limits = RunLimits::new(args.min_run, args.max_run)?;
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
-    let grid: Grid = input.parse()?;
-    let crucible = Crucible::new();
+    let mut grid: Grid = input.parse()?;
+    for position in args.forbid {
+        eyre::ensure!(
+            grid.get(position).is_some(),
+            "forbidden tile {position} is outside the grid"
+        );
+        grid.forbidden.insert(position);
+    }
+
+    let start = args.start.unwrap_or(Position { row: 0, col: 0 });
+    let goal = args.goal.unwrap_or_else(|| grid.end());
+    for (name, position) in [("start", start), ("goal", goal)] {
+        eyre::ensure!(
+            grid.get(position).is_some(),
+            "{name} {position} is outside the grid"
+        );
+        eyre::ensure!(
+            !grid.forbidden.contains(&position),
+            "{name} {position} is forbidden"
+        );
+    }
+
+    let crucible = Crucible::new(start);
+
+    let successors = |crucible: &Crucible| {
+        let candidates = crucible.move_candidates(&grid, &limits);
+        candidates.into_iter().map(|candidate| {
+            let heat_loss = candidate.heat_loss_at_position(&grid);
+            (candidate, heat_loss)
+        })
+    };
+    let is_finished = |crucible: &Crucible| crucible.is_finished(goal, &limits);
+
+    let path = match args.algorithm {
+        Algorithm::Dijkstra => dijkstra(&crucible, successors, is_finished),
+        Algorithm::Astar => {
+            let min_heat_loss = grid.rows.iter().flatten().copied().min().unwrap_or(0);
+            let heuristic = |crucible: &Crucible| {
+                let distance = crucible.position.row.abs_diff(goal.row)
+                    + crucible.position.col.abs_diff(goal.col);
+                let distance: u32 = distance.try_into().unwrap();
+                distance * min_heat_loss
+            };
+            astar(&crucible, successors, heuristic, is_finished)
+        }
+    };
+    let (path, total_heat_loss) = path.ok_or_eyre("no path found")?;
+
+    if args.show_path {
+        println!("{}", DisplayPath(&grid, &path));
+
+        let mut heat_loss_so_far = 0;
+        for (n, crucible) in path.iter().enumerate().skip(1) {
+            let heat_loss = crucible.heat_loss_at_position(&grid);
+            heat_loss_so_far += heat_loss;
+
+            let arrow = crucible.direction.map(|dir| dir.arrow()).unwrap_or(' ');
+            println!(
+                "step {n}: {arrow} to {}, run {}, heat loss {heat_loss} (total {heat_loss_so_far})",
+                crucible.position, crucible.run_length
+            );
+        }
+    }
 
-    let path = dijkstra(
-        &crucible,
-        |crucible| {
-            let candidates = crucible.move_candidates(&grid);
-            candidates.into_iter().map(|candidate| {
-                let heat_loss = candidate.heat_loss_at_position(&grid);
-                (candidate, heat_loss)
-            })
-        },
-        |crucible| crucible.is_finished(&grid),
-    );
-    let (_path, total_heat_loss) = path.ok_or_eyre("no path found")?;
     println!("{total_heat_loss}");
 
     Ok(())
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, PartialEq, Eq)]
 struct Grid {
     rows: Vec<Vec<u32>>,
+    forbidden: HashSet<Position>,
 }
 
 impl Grid {
@@ -70,7 +171,9 @@ impl Grid {
             },
         };
 
-        if self.get(new_pos).is_none() {
+        self.get(new_pos)?;
+
+        if self.forbidden.contains(&new_pos) {
             return None;
         }
 
@@ -82,7 +185,7 @@ impl Grid {
     }
 
     fn num_cols(&self) -> usize {
-        self.rows.get(0).map(|row| row.len()).unwrap_or(0)
+        self.rows.first().map(|row| row.len()).unwrap_or(0)
     }
 
     fn end(&self) -> Position {
@@ -93,6 +196,32 @@ impl Grid {
     }
 }
 
+struct DisplayPath<'a>(&'a Grid, &'a [Crucible]);
+
+impl std::fmt::Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self(grid, path) = self;
+        let arrows = path
+            .iter()
+            .filter_map(|crucible| Some((crucible.position, crucible.direction?.arrow())))
+            .collect::<HashMap<_, _>>();
+
+        for (row, cells) in grid.rows.iter().enumerate() {
+            for (col, heat_loss) in cells.iter().enumerate() {
+                let position = Position { row, col };
+                match arrows.get(&position) {
+                    Some(arrow) => write!(f, "{arrow}")?,
+                    None if grid.forbidden.contains(&position) => write!(f, "#")?,
+                    None => write!(f, "{heat_loss}")?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl std::str::FromStr for Grid {
     type Err = eyre::Error;
 
@@ -105,7 +234,10 @@ impl std::str::FromStr for Grid {
                     .collect::<eyre::Result<Vec<_>>>()
             })
             .collect::<eyre::Result<Vec<Vec<_>>>>()?;
-        Ok(Self { rows })
+        Ok(Self {
+            rows,
+            forbidden: HashSet::new(),
+        })
     }
 }
 
@@ -115,6 +247,23 @@ struct Position {
     col: usize,
 }
 
+impl std::str::FromStr for Position {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (row, col) = s.split_once(',').ok_or_eyre("expected `row,col`")?;
+        let row = row.trim().parse()?;
+        let col = col.trim().parse()?;
+        Ok(Self { row, col })
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.row, self.col)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Direction {
     North,
@@ -126,6 +275,15 @@ enum Direction {
 impl Direction {
     const DIRECTIONS: [Self; 4] = [Self::North, Self::South, Self::East, Self::West];
 
+    fn arrow(&self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::South => 'v',
+            Direction::East => '>',
+            Direction::West => '<',
+        }
+    }
+
     fn reverse(&self) -> Self {
         match self {
             Direction::North => Direction::South,
@@ -136,71 +294,89 @@ impl Direction {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy)]
+struct RunLimits {
+    min: usize,
+    max: usize,
+}
+
+impl RunLimits {
+    fn new(min: usize, max: usize) -> eyre::Result<Self> {
+        eyre::ensure!(max >= 1, "maximum run must be at least 1");
+        eyre::ensure!(min <= max, "minimum run must not exceed maximum run");
+        Ok(Self { min, max })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Crucible {
     position: Position,
-    direction_history: Vec<Direction>,
+
+    /// The direction of the last move, or `None` if the crucible hasn't
+    /// moved yet
+    direction: Option<Direction>,
+
+    /// How many tiles the crucible has moved in a straight line in its
+    /// current direction
+    run_length: usize,
 }
 
 impl Crucible {
-    fn new() -> Self {
+    fn new(position: Position) -> Self {
         Self {
-            position: Position { row: 0, col: 0 },
-            direction_history: vec![],
+            position,
+            direction: None,
+            run_length: 0,
         }
     }
 
-    fn move_candidates(&self, grid: &Grid) -> Vec<Self> {
+    fn move_candidates(&self, grid: &Grid, limits: &RunLimits) -> Vec<Self> {
         Direction::DIRECTIONS
             .into_iter()
-            .filter_map(|dir| {
-                let mut candidate = self.clone();
-                let is_valid = candidate.move_direction(grid, dir);
-                if is_valid {
-                    Some(candidate)
-                } else {
-                    None
-                }
-            })
+            .filter_map(|dir| self.move_direction(grid, limits, dir))
             .collect()
     }
 
-    fn move_direction(&mut self, grid: &Grid, direction: Direction) -> bool {
-        if let Some(last_movement) = self.direction_history.last() {
-            // Cancel if we just came from that direction
-            if *last_movement == direction.reverse() {
-                return false;
-            }
+    fn move_direction(
+        &self,
+        grid: &Grid,
+        limits: &RunLimits,
+        direction: Direction,
+    ) -> Option<Self> {
+        let run_length = match self.direction {
+            None => 1,
+            Some(last_direction) => {
+                // Cancel if we just came from that direction
+                if last_direction == direction.reverse() {
+                    return None;
+                }
 
-            let straight_line_distance = self
-                .direction_history
-                .iter()
-                .rev()
-                .take_while(|dir| **dir == *last_movement)
-                .count();
+                if last_direction == direction {
+                    // Cancel if we've been travelling in a straight line too long
+                    if self.run_length >= limits.max {
+                        return None;
+                    }
 
-            // Cancel if we've been travelling in a straight line too long
-            if *last_movement == direction && straight_line_distance >= 10 {
-                return false;
-            }
+                    self.run_length + 1
+                } else {
+                    // Cancel if we just turned and are trying to turn again
+                    if self.run_length < limits.min {
+                        return None;
+                    }
 
-            // Cancel if we just turned and are trying to turn again
-            if *last_movement != direction && straight_line_distance < 4 {
-                return false;
+                    1
+                }
             }
-        }
-
-        // Cancel if this movement takes us off the grid
-        let Some(new_position) = grid.move_position(self.position, direction) else {
-            return false;
         };
 
-        self.direction_history.push(direction);
-        self.direction_history
-            .splice(0..self.direction_history.len().saturating_sub(10), []);
-        self.position = new_position;
+        // Cancel if this movement takes us off the grid
+        let position = grid.move_position(self.position, direction)?;
 
-        true
+        Some(Self {
+            position,
+            direction: Some(direction),
+            run_length,
+        })
     }
 
     fn heat_loss_at_position(&self, grid: &Grid) -> u32 {
@@ -208,22 +384,13 @@ impl Crucible {
             .expect("crucible at invalid position")
     }
 
-    fn is_finished(&self, grid: &Grid) -> bool {
-        if let Some(last_direction) = self.direction_history.last() {
-            let straight_line_distance = self
-                .direction_history
-                .iter()
-                .rev()
-                .take_while(|dir| *dir == last_direction)
-                .count();
-
-            // Can't stop unless we've been travelling in the same line for at
-            // least 4 tiles
-            if straight_line_distance < 4 {
-                return false;
-            }
+    fn is_finished(&self, goal: Position, limits: &RunLimits) -> bool {
+        // Can't stop unless we've been travelling in the same line for long
+        // enough
+        if self.direction.is_some() && self.run_length < limits.min {
+            return false;
         }
 
-        self.position == grid.end()
+        self.position == goal
     }
 }
@@ -1,6 +1,41 @@
 use std::io::Read as _;
 
-use eyre::OptionExt;
+use clap::Parser as _;
+use eyre::{OptionExt, WrapErr as _};
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// Which columns of the dig plan to read each instruction from
+    #[arg(long, value_enum, default_value_t = Encoding::Hex)]
+    encoding: Encoding,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Print the volume of the lagoon (default)
+    Volume,
+
+    /// Render the dig plan as an SVG image, with each edge stroked in its
+    /// instruction's colour
+    Svg {
+        /// Size of the longest side of the image, in pixels
+        #[arg(long, default_value_t = 1000.0)]
+        size: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum Encoding {
+    /// Use the direction and distance columns, e.g. `R 6`
+    Plain,
+
+    /// Decode the direction and distance from the hex colour, e.g.
+    /// `(#70c710)`
+    Hex,
+}
 
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
@@ -13,52 +48,194 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
     let instructions: Vec<Instruction> = input
         .lines()
-        .map(|line| line.parse())
+        .enumerate()
+        .map(|(i, line)| {
+            Instruction::parse(line, args.encoding)
+                .wrap_err_with(|| format!("failed to parse line {}", i + 1))
+        })
         .collect::<eyre::Result<_>>()?;
 
-    let mut position = Position { x: 0.0, y: 0.0 };
+    match args.command.unwrap_or(Command::Volume) {
+        Command::Volume => {
+            validate_dig_plan(&instructions)?;
+
+            let volume = lagoon_volume(&instructions);
+            println!("{volume}");
+        }
+        Command::Svg { size } => {
+            eyre::ensure!(size > 0.0, "image size must be positive");
+            print!(
+                "{}",
+                DigPlanSvg {
+                    instructions: &instructions,
+                    size
+                }
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the corner of each trench edge, starting and ending at the
+/// origin for a closed dig plan.
+fn dig_path(instructions: &[Instruction]) -> Vec<Position> {
+    let mut position = Position { x: 0, y: 0 };
     let mut points = vec![position];
-    let mut perimieter = 0.0;
-    for instruction in &instructions {
-        let new_pos = position.offset(instruction.direction, instruction.distance);
-        perimieter += instruction.distance;
+    for instruction in instructions {
+        position = position.offset(instruction.direction, instruction.distance);
+        points.push(position);
+    }
+
+    points
+}
 
-        points.push(new_pos);
-        position = new_pos;
+/// Checks that the dig plan describes a simple closed loop, since the
+/// shoelace formula gives a meaningless area otherwise. All problems are
+/// reported together, using 1-based line numbers from the input.
+fn validate_dig_plan(instructions: &[Instruction]) -> eyre::Result<()> {
+    let points = dig_path(instructions);
+    let edges: Vec<_> = points
+        .windows(2)
+        .map(|edge| Edge {
+            start: edge[0],
+            end: edge[1],
+        })
+        .collect();
+    let mut problems = vec![];
+
+    for (i, instruction) in instructions.iter().enumerate() {
+        if instruction.distance == 0 {
+            problems.push(format!("line {}: zero-length move", i + 1));
+        }
     }
 
-    let inner_volume = polygon_area(&points);
-    let full_volume = inner_volume + (perimieter / 2.0) + 1.0;
-    println!("{full_volume}");
+    let end = points.last().copied().unwrap_or(Position { x: 0, y: 0 });
+    let is_closed = end == (Position { x: 0, y: 0 });
+    if !is_closed {
+        problems.push(format!(
+            "path ends at {end} instead of returning to the origin"
+        ));
+    }
+
+    for i in 0..edges.len() {
+        for j in (i + 1)..edges.len() {
+            let is_adjacent = j == i + 1 || (is_closed && i == 0 && j == edges.len() - 1);
+            let intersects = if is_adjacent {
+                // Neighbouring edges always share a corner, so they only
+                // cross if the second one doubles back over the first
+                instructions[i].direction == instructions[j].direction.opposite()
+            } else {
+                edges[i].intersects(&edges[j])
+            };
+
+            if intersects {
+                problems.push(format!(
+                    "line {}: edge intersects the edge from line {}",
+                    j + 1,
+                    i + 1
+                ));
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        eyre::bail!("invalid dig plan:\n{}", problems.join("\n"));
+    }
 
     Ok(())
 }
 
-fn polygon_area(points: &[Position]) -> f64 {
-    let mut area = 0.0;
+fn lagoon_volume(instructions: &[Instruction]) -> i128 {
+    let mut points = dig_path(instructions);
+    normalize_winding(&mut points);
+    let perimeter: i128 = instructions
+        .iter()
+        .map(|instruction| i128::from(instruction.distance))
+        .sum();
+
+    // Pick's theorem relates the polygon's area to the number of tiles
+    // inside the trench, and each tile of the trench itself also counts
+    // towards the volume:
+    //
+    // area = interior + (perimeter / 2) - 1
+    // volume = interior + perimeter
+    //        = area + (perimeter / 2) + 1
+    let twice_area = twice_polygon_area(&points);
+    (twice_area + perimeter) / 2 + 1
+}
+
+/// Reorders the points of a polygon so they wind counterclockwise (with
+/// `y` pointing down), making its signed area positive.
+fn normalize_winding(points: &mut [Position]) {
+    if twice_polygon_area(points) < 0 {
+        tracing::debug!("dig plan winds clockwise, reversing");
+        points.reverse();
+    }
+}
+
+/// Returns twice the signed area of a polygon using the shoelace formula,
+/// which is always a whole number for points with integer coordinates.
+/// The sign depends on the polygon's winding direction.
+fn twice_polygon_area(points: &[Position]) -> i128 {
+    let mut area: i128 = 0;
     for i in 0..points.len() {
         let j = (i + 1) % points.len();
-        area = area + points[i].x as f64 * points[j].y as f64;
-        area = area - points[i].y as f64 * points[j].x as f64;
+        area += i128::from(points[i].x) * i128::from(points[j].y);
+        area -= i128::from(points[i].y) * i128::from(points[j].x);
     }
 
-    area / 2.0
+    area
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Position {
-    x: f64,
-    y: f64,
+    x: i64,
+    y: i64,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// An axis-aligned edge of the trench.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    start: Position,
+    end: Position,
+}
+
+impl Edge {
+    fn intersects(&self, other: &Edge) -> bool {
+        // Both edges are axis-aligned, so they touch exactly when their
+        // bounding boxes overlap
+        let (min_x, max_x) = (self.start.x.min(self.end.x), self.start.x.max(self.end.x));
+        let (min_y, max_y) = (self.start.y.min(self.end.y), self.start.y.max(self.end.y));
+        let (other_min_x, other_max_x) = (
+            other.start.x.min(other.end.x),
+            other.start.x.max(other.end.x),
+        );
+        let (other_min_y, other_max_y) = (
+            other.start.y.min(other.end.y),
+            other.start.y.max(other.end.y),
+        );
+
+        min_x <= other_max_x && other_min_x <= max_x && min_y <= other_max_y && other_min_y <= max_y
+    }
 }
 
 impl Position {
-    fn offset(&self, direction: Direction, distance: f64) -> Position {
+    fn offset(&self, direction: Direction, distance: i64) -> Position {
         match direction {
             Direction::Up => Position {
                 x: self.x,
@@ -83,17 +260,17 @@ impl Position {
 #[derive(Debug, Clone, Copy)]
 struct Instruction {
     direction: Direction,
-    distance: f64,
+    distance: i64,
+    colour: Colour,
 }
 
-impl std::str::FromStr for Instruction {
-    type Err = eyre::Error;
+impl Instruction {
+    fn parse(s: &str, encoding: Encoding) -> eyre::Result<Self> {
+        let mut columns = s.split_whitespace();
+        let direction = columns.next().ok_or_eyre("invalid instruction")?;
+        let distance = columns.next().ok_or_eyre("invalid instruction")?;
+        let hex_string = columns.next().ok_or_eyre("invalid instruction")?;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let hex_string = s
-            .split_whitespace()
-            .nth(2)
-            .ok_or_eyre("invalid instruction")?;
         let hex_string = hex_string
             .strip_prefix('(')
             .ok_or_eyre("invalid instruction")?
@@ -102,7 +279,25 @@ impl std::str::FromStr for Instruction {
         let hex_digits = hex_string
             .strip_prefix('#')
             .ok_or_eyre("invalid instruction")?;
+        eyre::ensure!(
+            hex_digits.len() == 6 && hex_digits.chars().all(|c| c.is_ascii_hexdigit()),
+            "invalid hex: {hex_digits:?}"
+        );
+        let colour = Colour(u32::from_str_radix(hex_digits, 16)?);
+
+        let (direction, distance) = match encoding {
+            Encoding::Plain => (direction.parse()?, distance.parse::<u32>()?.into()),
+            Encoding::Hex => Self::parse_hex(hex_digits)?,
+        };
 
+        Ok(Self {
+            direction,
+            distance,
+            colour,
+        })
+    }
+
+    fn parse_hex(hex_digits: &str) -> eyre::Result<(Direction, i64)> {
         let (distance_hex, direction_hex) = hex_digits.split_at(5);
         let direction = match direction_hex {
             "0" => Direction::Right,
@@ -113,17 +308,81 @@ impl std::str::FromStr for Instruction {
                 eyre::bail!("invalid direction hex: {other:?}");
             }
         };
-        let distance = u64::from_str_radix(distance_hex, 16)?;
-        let distance = distance as f64;
+        let distance = i64::from_str_radix(distance_hex, 16)?;
 
-        Ok(Self {
-            direction,
-            distance,
-        })
+        Ok((direction, distance))
     }
 }
 
 #[derive(Debug, Clone, Copy)]
+struct Colour(u32);
+
+impl std::fmt::Display for Colour {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:06x}", self.0)
+    }
+}
+
+/// Renders a dig plan as SVG. The plan is scaled so its longest side fits
+/// within `size` pixels, so even plans with huge distances stay viewable.
+struct DigPlanSvg<'a> {
+    instructions: &'a [Instruction],
+    size: f64,
+}
+
+impl std::fmt::Display for DigPlanSvg<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let points = dig_path(self.instructions);
+        let min_x = points.iter().map(|p| p.x).min().unwrap_or(0);
+        let max_x = points.iter().map(|p| p.x).max().unwrap_or(0);
+        let min_y = points.iter().map(|p| p.y).min().unwrap_or(0);
+        let max_y = points.iter().map(|p| p.y).max().unwrap_or(0);
+
+        let plan_width = (max_x - min_x).max(1) as f64;
+        let plan_height = (max_y - min_y).max(1) as f64;
+        let scale = self.size / plan_width.max(plan_height);
+
+        // Leave a margin so strokes along the bounding box aren't clipped
+        let stroke_width = (self.size / 200.0).max(1.0);
+        let margin = stroke_width;
+        let width = plan_width * scale + 2.0 * margin;
+        let height = plan_height * scale + 2.0 * margin;
+
+        let project = |p: Position| {
+            (
+                (p.x - min_x) as f64 * scale + margin,
+                (p.y - min_y) as f64 * scale + margin,
+            )
+        };
+
+        writeln!(
+            f,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width:.3} {height:.3}">"#
+        )?;
+
+        write!(f, r##"  <polygon fill="#d0d0d0" stroke="none" points=""##)?;
+        for (i, &point) in points.iter().enumerate() {
+            let (x, y) = project(point);
+            let separator = if i == 0 { "" } else { " " };
+            write!(f, "{separator}{x:.3},{y:.3}")?;
+        }
+        writeln!(f, r#""/>"#)?;
+
+        for (instruction, edge) in self.instructions.iter().zip(points.windows(2)) {
+            let (x1, y1) = project(edge[0]);
+            let (x2, y2) = project(edge[1]);
+            writeln!(
+                f,
+                r#"  <line x1="{x1:.3}" y1="{y1:.3}" x2="{x2:.3}" y2="{y2:.3}" stroke="{}" stroke-width="{stroke_width:.3}" stroke-linecap="square"/>"#,
+                instruction.colour
+            )?;
+        }
+
+        writeln!(f, "</svg>")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Up,
     Down,
@@ -131,6 +390,17 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn opposite(&self) -> Self {
+        match self {
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
 impl std::str::FromStr for Direction {
     type Err = eyre::Error;
 
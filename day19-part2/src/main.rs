@@ -1,7 +1,57 @@
-use std::{collections::HashMap, io::Read as _, ops::RangeInclusive};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read as _,
+    ops::RangeInclusive,
+};
 
+use clap::Parser as _;
 use eyre::OptionExt;
 
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// The range of `x` ratings a part can have, as `MIN-MAX`
+    #[arg(long, value_parser = parse_range, default_value = "1-4000")]
+    x: RangeInclusive<u16>,
+
+    /// The range of `m` ratings a part can have, as `MIN-MAX`
+    #[arg(long, value_parser = parse_range, default_value = "1-4000")]
+    m: RangeInclusive<u16>,
+
+    /// The range of `a` ratings a part can have, as `MIN-MAX`
+    #[arg(long, value_parser = parse_range, default_value = "1-4000")]
+    a: RangeInclusive<u16>,
+
+    /// The range of `s` ratings a part can have, as `MIN-MAX`
+    #[arg(long, value_parser = parse_range, default_value = "1-4000")]
+    s: RangeInclusive<u16>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Count every combination of ratings that would be accepted (default)
+    Count,
+
+    /// Run each listed part through the workflows, printing the workflows
+    /// it passed through and the total rating of the accepted parts
+    Eval,
+
+    /// Print an equivalent, simplified set of workflows, dropping rules that
+    /// can't change the outcome for parts within the rating ranges
+    Simplify,
+}
+
+fn parse_range(s: &str) -> eyre::Result<RangeInclusive<u16>> {
+    let (min, max) = s.split_once('-').ok_or_eyre("expected `MIN-MAX`")?;
+    let min = min.parse()?;
+    let max = max.parse()?;
+    eyre::ensure!(min <= max, "range {min}-{max} is empty");
+
+    Ok(min..=max)
+}
+
 fn main() -> eyre::Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -13,35 +63,66 @@ fn main() -> eyre::Result<()> {
         .init();
     color_eyre::install()?;
 
+    let args = Args::parse();
+
     let mut stdin = std::io::stdin().lock();
     let mut input = String::new();
     stdin.read_to_string(&mut input)?;
 
-    let Some((workflows, _parts)) = input.split_once("\n\n") else {
+    let Some((workflows, parts)) = input.split_once("\n\n") else {
         eyre::bail!("invalid input");
     };
     let workflows = Workflows::parse(workflows)?;
+    workflows.validate()?;
+    let parts = parts
+        .lines()
+        .map(|line| line.parse())
+        .collect::<eyre::Result<Vec<Part>>>()?;
+
+    let all_parts = PartSet {
+        x: args.x,
+        m: args.m,
+        a: args.a,
+        s: args.s,
+    };
 
-    let rules = workflows.to_rule();
-    let total_possible_parts: u64 = rules
-        .filter_parts(&PartSet::all())
-        .into_iter()
-        .map(|parts| parts.len())
-        .sum();
+    match args.command.unwrap_or(Command::Count) {
+        Command::Count => {
+            let rules = workflows.to_rule();
+            let total_possible_parts: u64 = rules
+                .filter_parts(&all_parts)
+                .into_iter()
+                .map(|parts| parts.len())
+                .sum();
 
-    println!("{:#?}", total_possible_parts);
+            println!("{total_possible_parts}");
+        }
+        Command::Eval => {
+            let mut value = 0;
+            for part in &parts {
+                let evaluation = workflows.eval(part);
+                let outcome = if evaluation.accepted { "A" } else { "R" };
+                println!("{part}: {} -> {outcome}", evaluation.path.join(" -> "));
+
+                if evaluation.accepted {
+                    value += part.value();
+                }
+            }
 
-    // let value: i64 = parts
-    //     .iter()
-    //     .filter(|part| workflows.eval(part))
-    //     .map(|part| part.value())
-    //     .sum();
-    // println!("{value}");
+            println!("{value}");
+        }
+        Command::Simplify => {
+            let simplified = workflows.simplify(&all_parts);
+            for name in simplified.topological_order() {
+                println!("{}", simplified.workflows[name]);
+            }
+        }
+    }
 
     Ok(())
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Workflows {
     workflows: HashMap<String, Workflow>,
 }
@@ -59,6 +140,229 @@ impl Workflows {
         Ok(Self { workflows })
     }
 
+    /// Checks the call graph between workflows, so that evaluation can
+    /// assume every called workflow exists and that no part can loop
+    /// forever. Unreachable workflows are only reported as warnings.
+    fn validate(&self) -> eyre::Result<()> {
+        let mut names: Vec<&str> = self.workflows.keys().map(|name| &**name).collect();
+        names.sort_unstable();
+
+        let mut problems = vec![];
+
+        if !self.workflows.contains_key("in") {
+            problems.push("missing starting workflow `in`".to_string());
+        }
+
+        for &name in &names {
+            for called in self.workflows[name].rule.calls() {
+                if !self.workflows.contains_key(called) {
+                    problems.push(format!(
+                        "workflow `{name}` calls undefined workflow `{called}`"
+                    ));
+                }
+            }
+        }
+
+        let mut reachable = HashSet::new();
+        let mut queue = vec!["in"];
+        while let Some(name) = queue.pop() {
+            let Some(workflow) = self.workflows.get(name) else {
+                continue;
+            };
+            if reachable.insert(name) {
+                queue.extend(workflow.rule.calls());
+            }
+        }
+        for &name in &names {
+            if !reachable.contains(name) {
+                tracing::warn!("workflow `{name}` is unreachable from `in`");
+            }
+        }
+
+        for cycle in self.cycles(&names) {
+            problems.push(format!("workflows form a cycle: {}", cycle.join(" -> ")));
+        }
+
+        if !problems.is_empty() {
+            eyre::bail!("invalid workflows:\n{}", problems.join("\n"));
+        }
+
+        Ok(())
+    }
+
+    /// Finds cycles in the call graph using a depth-first search from each
+    /// workflow. Each cycle is returned as a path that starts and ends with
+    /// the same workflow.
+    fn cycles<'a>(&'a self, names: &[&'a str]) -> Vec<Vec<&'a str>> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum Visit {
+            InProgress,
+            Done,
+        }
+
+        let mut visits: HashMap<&str, Visit> = HashMap::new();
+        let mut cycles = vec![];
+
+        for &root in names {
+            if visits.contains_key(root) {
+                continue;
+            }
+
+            // Use an explicit stack so a long chain of workflows can't
+            // overflow the real one
+            let mut stack = vec![(root, self.workflows[root].rule.calls())];
+            visits.insert(root, Visit::InProgress);
+
+            while let Some((name, calls)) = stack.last_mut() {
+                let name = *name;
+                let Some(called) = calls.pop() else {
+                    visits.insert(name, Visit::Done);
+                    stack.pop();
+                    continue;
+                };
+                let Some(workflow) = self.workflows.get(called) else {
+                    continue;
+                };
+
+                match visits.get(called) {
+                    Some(Visit::Done) => {}
+                    Some(Visit::InProgress) => {
+                        let start = stack
+                            .iter()
+                            .position(|(name, _)| *name == called)
+                            .expect("in-progress workflow not on stack");
+                        let mut cycle: Vec<_> =
+                            stack[start..].iter().map(|(name, _)| *name).collect();
+                        cycle.push(called);
+                        cycles.push(cycle);
+                    }
+                    None => {
+                        visits.insert(called, Visit::InProgress);
+                        stack.push((called, workflow.rule.calls()));
+                    }
+                }
+            }
+        }
+
+        cycles
+    }
+
+    /// Returns every workflow reachable from `in`, ordered so that each
+    /// workflow comes before any workflow it calls. Assumes the workflows
+    /// have been validated, so the call graph has no cycles.
+    fn topological_order(&self) -> Vec<&str> {
+        let mut visited = HashSet::new();
+        let mut order = vec![];
+
+        let mut stack = vec![("in", self.workflows["in"].rule.calls())];
+        visited.insert("in");
+        while let Some((name, calls)) = stack.last_mut() {
+            let name = *name;
+            let Some(called) = calls.pop() else {
+                order.push(name);
+                stack.pop();
+                continue;
+            };
+
+            if visited.insert(called) {
+                stack.push((called, self.workflows[called].rule.calls()));
+            }
+        }
+
+        order.reverse();
+        order
+    }
+
+    /// Repeatedly simplifies each workflow until nothing changes. The
+    /// ratings a workflow can see are approximated by the smallest
+    /// `PartSet` covering every call to it, so any rule that's dead for
+    /// that set is dead for every part that reaches it.
+    fn simplify(&self, all_parts: &PartSet) -> Workflows {
+        let mut workflows = self.clone();
+        loop {
+            let simplified = workflows.simplify_once(all_parts);
+            if simplified == workflows {
+                return simplified;
+            }
+
+            workflows = simplified;
+        }
+    }
+
+    fn simplify_once(&self, all_parts: &PartSet) -> Workflows {
+        let order = self.topological_order();
+
+        let mut reachable_parts: HashMap<&str, PartSet> = HashMap::new();
+        reachable_parts.insert("in", all_parts.clone());
+        for &name in &order {
+            // Workflows that are only called for parts that can't exist
+            // won't be called once their callers are simplified
+            let Some(parts) = reachable_parts.get(name).cloned() else {
+                continue;
+            };
+            for (called, called_parts) in self.workflows[name].rule.call_sets(parts) {
+                reachable_parts
+                    .entry(called)
+                    .and_modify(|parts| *parts = parts.hull(&called_parts))
+                    .or_insert(called_parts);
+            }
+        }
+
+        // Simplify callees first, so calls to workflows that always accept
+        // or always reject can be replaced with the outcome directly
+        let mut workflows = HashMap::new();
+        for &name in order.iter().rev() {
+            let Some(parts) = reachable_parts.get(name).cloned() else {
+                continue;
+            };
+            let rule = self.workflows[name].rule.simplify(parts, &workflows);
+            let workflow = Workflow {
+                name: name.to_string(),
+                rule,
+            };
+            workflows.insert(name.to_string(), workflow);
+        }
+
+        // Drop any workflows that are no longer called
+        let simplified = Workflows { workflows };
+        let reachable: HashSet<_> = simplified.topological_order().into_iter().collect();
+        let workflows = simplified
+            .workflows
+            .iter()
+            .filter(|(name, _)| reachable.contains(&***name))
+            .map(|(name, workflow)| (name.clone(), workflow.clone()))
+            .collect();
+
+        Workflows { workflows }
+    }
+
+    fn eval(&self, part: &Part) -> Evaluation {
+        let mut path = vec![];
+        let mut workflow_name = "in";
+        loop {
+            path.push(workflow_name.to_string());
+            let workflow = &self.workflows[workflow_name];
+
+            match workflow.rule.eval(part) {
+                RuleResult::Accept => {
+                    return Evaluation {
+                        path,
+                        accepted: true,
+                    };
+                }
+                RuleResult::Reject => {
+                    return Evaluation {
+                        path,
+                        accepted: false,
+                    };
+                }
+                RuleResult::Call(next_workflow) => {
+                    workflow_name = next_workflow;
+                }
+            }
+        }
+    }
+
     fn to_rule(&self) -> PartRule {
         self.workflow_to_rule("in")
     }
@@ -79,7 +383,7 @@ impl Workflows {
                 then,
                 else_,
             } => PartRule::If {
-                condition: condition.clone(),
+                condition: *condition,
                 then: Box::new(self.convert_rule(then)),
                 else_: Box::new(self.convert_rule(else_)),
             },
@@ -87,12 +391,18 @@ impl Workflows {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Workflow {
     name: String,
     rule: Rule,
 }
 
+impl std::fmt::Display for Workflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{{{}}}", self.name, self.rule)
+    }
+}
+
 impl std::str::FromStr for Workflow {
     type Err = eyre::Error;
 
@@ -108,7 +418,7 @@ impl std::str::FromStr for Workflow {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum Rule {
     Accept,
     Reject,
@@ -120,6 +430,160 @@ enum Rule {
     },
 }
 
+impl Rule {
+    /// Returns the name of every workflow this rule can call, in reverse
+    /// order.
+    fn calls(&self) -> Vec<&str> {
+        let mut calls = vec![];
+        let mut rules = vec![self];
+        while let Some(rule) = rules.pop() {
+            match rule {
+                Rule::Accept | Rule::Reject => {}
+                Rule::Call(workflow) => calls.push(&**workflow),
+                Rule::If { then, else_, .. } => {
+                    rules.push(then);
+                    rules.push(else_);
+                }
+            }
+        }
+
+        calls
+    }
+
+    /// Returns each workflow this rule can call, along with the set of
+    /// parts that can reach that call.
+    fn call_sets(&self, parts: PartSet) -> Vec<(&str, PartSet)> {
+        let mut call_sets = vec![];
+        let mut rules = vec![(self, parts)];
+        while let Some((rule, parts)) = rules.pop() {
+            match rule {
+                Rule::Accept | Rule::Reject => {}
+                Rule::Call(workflow) => call_sets.push((&**workflow, parts)),
+                Rule::If {
+                    condition,
+                    then,
+                    else_,
+                } => {
+                    let (then_set, else_set) = parts.split(*condition);
+                    rules.extend(then_set.map(|then_set| (&**then, then_set)));
+                    rules.extend(else_set.map(|else_set| (&**else_, else_set)));
+                }
+            }
+        }
+
+        call_sets
+    }
+
+    /// Returns an equivalent rule for the parts in `parts`. `simplified`
+    /// holds the workflows that have already been simplified, so calls to
+    /// workflows with a fixed outcome or that just call another workflow
+    /// can be inlined.
+    fn simplify(&self, parts: PartSet, simplified: &HashMap<String, Workflow>) -> Rule {
+        match self {
+            Rule::Accept => Rule::Accept,
+            Rule::Reject => Rule::Reject,
+            Rule::Call(workflow) => match simplified.get(workflow).map(|workflow| &workflow.rule) {
+                Some(Rule::Accept) => Rule::Accept,
+                Some(Rule::Reject) => Rule::Reject,
+                Some(Rule::Call(next_workflow)) => Rule::Call(next_workflow.clone()),
+                _ => Rule::Call(workflow.clone()),
+            },
+            Rule::If {
+                condition,
+                then,
+                else_,
+            } => match parts.split(*condition) {
+                (Some(then_set), Some(else_set)) => Rule::simplified_if(
+                    *condition,
+                    then.simplify(then_set, simplified),
+                    else_.simplify(else_set, simplified),
+                ),
+                // The condition always holds, or can never hold
+                (Some(then_set), None) => then.simplify(then_set, simplified),
+                (None, Some(else_set)) => else_.simplify(else_set, simplified),
+                (None, None) => unreachable!("split an empty set of parts"),
+            },
+        }
+    }
+
+    /// Builds an `If` rule, folding it away if both branches are the same
+    /// and merging it with the next comparison if that checks the same
+    /// variable in the same direction and leads to the same outcome.
+    fn simplified_if(condition: Condition, then: Rule, else_: Rule) -> Rule {
+        if then == else_ {
+            return then;
+        }
+
+        if let Rule::If {
+            condition: next_condition,
+            then: next_then,
+            else_: next_else,
+        } = &else_
+        {
+            if **next_then == then {
+                if let Some(merged) = condition.merge(*next_condition) {
+                    return Rule::simplified_if(merged, then, (**next_else).clone());
+                }
+            }
+        }
+
+        Rule::If {
+            condition,
+            then: Box::new(then),
+            else_: Box::new(else_),
+        }
+    }
+
+    fn eval(&self, part: &Part) -> RuleResult<'_> {
+        match self {
+            Rule::Accept => RuleResult::Accept,
+            Rule::Reject => RuleResult::Reject,
+            Rule::Call(workflow) => RuleResult::Call(workflow),
+            Rule::If {
+                condition,
+                then,
+                else_,
+            } => {
+                if condition.eval(part) {
+                    then.eval(part)
+                } else {
+                    else_.eval(part)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum RuleResult<'a> {
+    Accept,
+    Reject,
+    Call(&'a str),
+}
+
+/// The outcome of running a single part through the workflows.
+#[derive(Debug, Clone)]
+struct Evaluation {
+    /// The name of each workflow the part passed through, starting with `in`
+    path: Vec<String>,
+    accepted: bool,
+}
+
+impl std::fmt::Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rule::Accept => write!(f, "A"),
+            Rule::Reject => write!(f, "R"),
+            Rule::Call(workflow) => write!(f, "{workflow}"),
+            Rule::If {
+                condition,
+                then,
+                else_,
+            } => write!(f, "{condition}:{then},{else_}"),
+        }
+    }
+}
+
 impl std::str::FromStr for Rule {
     type Err = eyre::Error;
 
@@ -148,13 +612,47 @@ impl std::str::FromStr for Rule {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Condition {
     var: Var,
     comparison: Comparison,
     value: u16,
 }
 
+impl Condition {
+    fn eval(&self, part: &Part) -> bool {
+        let var_value = part.get(self.var);
+
+        match self.comparison {
+            Comparison::Gt => var_value > self.value,
+            Comparison::Lt => var_value < self.value,
+        }
+    }
+}
+
+impl Condition {
+    /// Combines two comparisons of the same variable in the same direction
+    /// into one that holds whenever either of them does.
+    fn merge(&self, other: Condition) -> Option<Condition> {
+        if self.var != other.var || self.comparison != other.comparison {
+            return None;
+        }
+
+        let value = match self.comparison {
+            Comparison::Gt => self.value.min(other.value),
+            Comparison::Lt => self.value.max(other.value),
+        };
+
+        Some(Condition { value, ..*self })
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.var, self.comparison, self.value)
+    }
+}
+
 impl std::str::FromStr for Condition {
     type Err = eyre::Error;
 
@@ -174,7 +672,7 @@ impl std::str::FromStr for Condition {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Var {
     X,
     M,
@@ -182,6 +680,17 @@ enum Var {
     S,
 }
 
+impl std::fmt::Display for Var {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Var::X => write!(f, "x"),
+            Var::M => write!(f, "m"),
+            Var::A => write!(f, "a"),
+            Var::S => write!(f, "s"),
+        }
+    }
+}
+
 impl std::str::FromStr for Var {
     type Err = eyre::Error;
 
@@ -198,12 +707,21 @@ impl std::str::FromStr for Var {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Comparison {
     Gt,
     Lt,
 }
 
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Comparison::Gt => write!(f, ">"),
+            Comparison::Lt => write!(f, "<"),
+        }
+    }
+}
+
 impl std::str::FromStr for Comparison {
     type Err = eyre::Error;
 
@@ -218,6 +736,54 @@ impl std::str::FromStr for Comparison {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+struct Part {
+    x: u16,
+    m: u16,
+    a: u16,
+    s: u16,
+}
+
+impl Part {
+    fn get(&self, var: Var) -> u16 {
+        match var {
+            Var::X => self.x,
+            Var::M => self.m,
+            Var::A => self.a,
+            Var::S => self.s,
+        }
+    }
+
+    fn value(&self) -> u64 {
+        u64::from(self.x) + u64::from(self.m) + u64::from(self.a) + u64::from(self.s)
+    }
+}
+
+impl std::fmt::Display for Part {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{x={},m={},a={},s={}}}", self.x, self.m, self.a, self.s)
+    }
+}
+
+impl std::str::FromStr for Part {
+    type Err = eyre::Error;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let string = string.strip_prefix("{x=").ok_or_eyre("invalid part")?;
+        let (x, string) = string.split_once(",m=").ok_or_eyre("invalid part")?;
+        let (m, string) = string.split_once(",a=").ok_or_eyre("invalid part")?;
+        let (a, string) = string.split_once(",s=").ok_or_eyre("invalid part")?;
+        let s = string.strip_suffix('}').ok_or_eyre("invalid part")?;
+
+        let x = x.parse()?;
+        let m = m.parse()?;
+        let a = a.parse()?;
+        let s = s.parse()?;
+
+        Ok(Self { x, m, a, s })
+    }
+}
+
 #[derive(Debug, Clone)]
 enum PartRule {
     If {
@@ -238,13 +804,18 @@ impl PartRule {
                 else_,
             } => {
                 let (then_set, else_set) = parts.split(*condition);
-                let mut parts = then.filter_parts(&then_set);
-                parts.extend_from_slice(&else_.filter_parts(&else_set));
+                let mut parts = vec![];
+                if let Some(then_set) = then_set {
+                    parts.extend(then.filter_parts(&then_set));
+                }
+                if let Some(else_set) = else_set {
+                    parts.extend(else_.filter_parts(&else_set));
+                }
 
                 parts
             }
             PartRule::Accept => vec![parts.clone()],
-            PartRule::Reject => vec![PartSet::none()],
+            PartRule::Reject => vec![],
         }
     }
 }
@@ -258,24 +829,6 @@ struct PartSet {
 }
 
 impl PartSet {
-    fn all() -> Self {
-        PartSet {
-            x: 1..=4000,
-            m: 1..=4000,
-            a: 1..=4000,
-            s: 1..=4000,
-        }
-    }
-
-    fn none() -> Self {
-        PartSet {
-            x: 1..=0,
-            m: 1..=0,
-            a: 1..=0,
-            s: 1..=0,
-        }
-    }
-
     fn len(&self) -> u64 {
         self.x.len() as u64 * self.m.len() as u64 * self.a.len() as u64 * self.s.len() as u64
     }
@@ -299,12 +852,28 @@ impl PartSet {
         }
     }
 
-    fn split(&self, condition: Condition) -> (Self, Self) {
+    /// Returns the smallest set containing both sets.
+    fn hull(&self, other: &PartSet) -> PartSet {
+        let hull_range = |a: &RangeInclusive<u16>, b: &RangeInclusive<u16>| {
+            *a.start().min(b.start())..=*a.end().max(b.end())
+        };
+
+        PartSet {
+            x: hull_range(&self.x, &other.x),
+            m: hull_range(&self.m, &other.m),
+            a: hull_range(&self.a, &other.a),
+            s: hull_range(&self.s, &other.s),
+        }
+    }
+
+    /// Splits the set into the parts that match the condition and the parts
+    /// that don't, returning `None` for either side if it would be empty.
+    fn split(&self, condition: Condition) -> (Option<Self>, Option<Self>) {
         let range = self.range(condition.var);
-        let (then, else_) = split_range(&range, condition.comparison, condition.value);
+        let (then, else_) = split_range(range, condition.comparison, condition.value);
         (
-            self.with_range(condition.var, then),
-            self.with_range(condition.var, else_),
+            then.map(|then| self.with_range(condition.var, then)),
+            else_.map(|else_| self.with_range(condition.var, else_)),
         )
     }
 }
@@ -313,10 +882,21 @@ fn split_range(
     range: &RangeInclusive<u16>,
     comparison: Comparison,
     value: u16,
-) -> (RangeInclusive<u16>, RangeInclusive<u16>) {
-    match comparison {
-        Comparison::Gt => (value + 1..=*range.end(), *range.start()..=value),
-        Comparison::Lt if value == 0 => (1..=0, range.clone()),
-        Comparison::Lt => (*range.start()..=value - 1, value..=*range.end()),
-    }
+) -> (Option<RangeInclusive<u16>>, Option<RangeInclusive<u16>>) {
+    let (start, end) = (*range.start(), *range.end());
+    let (then, else_) = match comparison {
+        Comparison::Gt => (
+            value.checked_add(1).map(|min| min.max(start)..=end),
+            Some(start..=value.min(end)),
+        ),
+        Comparison::Lt => (
+            value.checked_sub(1).map(|max| start..=max.min(end)),
+            Some(value.max(start)..=end),
+        ),
+    };
+
+    (
+        then.filter(|range| !range.is_empty()),
+        else_.filter(|range| !range.is_empty()),
+    )
 }